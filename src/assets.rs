@@ -1,12 +1,59 @@
-use bevy::prelude::*;
+//! Assets are loaded in [`GameState::Loading`], with a
+//! progress bar on screen. Once they're all in the game
+//! continues to the menu, or to an error screen listing
+//! whatever couldn't be loaded. That includes the
+//! gameplay rules read from data files.
+use bevy::{asset::LoadState, prelude::*};
 use bevy_asset_loader::prelude::*;
 
+use crate::{
+    scoring::{ScoringRules, ScoringRulesLoader},
+    GameState, StateScoped,
+};
+
 pub struct AssetsPlugin;
 
 impl Plugin for AssetsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_collection::<ImageAssets>();
-        // .init_collection::<AudioAssets>();
+        app.add_asset::<ScoringRules>()
+            .init_asset_loader::<ScoringRulesLoader>()
+            .add_loading_state(
+                LoadingState::new(GameState::Loading)
+                    .continue_to_state(GameState::Menu)
+                    .on_failure_continue_to_state(
+                        GameState::LoadingFailed,
+                    ),
+            )
+            .add_collection_to_loading_state::<_, ImageAssets>(
+                GameState::Loading,
+            )
+            .add_collection_to_loading_state::<_, FontAssets>(
+                GameState::Loading,
+            )
+            .add_collection_to_loading_state::<_, RulesAssets>(
+                GameState::Loading,
+            )
+            // .add_collection_to_loading_state::<_, AudioAssets>(
+            //     GameState::Loading,
+            // )
+            .add_systems(
+                (track_assets, loading_screen)
+                    .in_schedule(OnEnter(GameState::Loading)),
+            )
+            .add_system(
+                apply_rules
+                    .run_if(resource_exists::<RulesAssets>())
+                    .in_schedule(OnExit(GameState::Loading)),
+            )
+            .add_system(
+                update_loading_bar
+                    .in_set(OnUpdate(GameState::Loading)),
+            )
+            .add_system(
+                loading_failed_screen.in_schedule(OnEnter(
+                    GameState::LoadingFailed,
+                )),
+            );
     }
 }
 
@@ -22,6 +69,10 @@ pub struct ImageAssets {
     pub button_pressed: Handle<Image>,
     #[asset(path = "particles/circle_01.png")]
     pub ball_hit: Handle<Image>,
+    #[asset(path = "particles/fire_01.png")]
+    pub fire_trail: Handle<Image>,
+    #[asset(path = "particles/fire_02.png")]
+    pub fire_glow: Handle<Image>,
     // #[asset(path = "grey_box.png")]
     // pub box_unchecked: Handle<Image>,
     // #[asset(path = "green_boxCheckmark.png")]
@@ -38,6 +89,24 @@ pub struct ImageAssets {
     // pub grass: Handle<TextureAtlas>,
 }
 
+/// Path of [`FontAssets::main`], for the screens shown
+/// before the collection exists
+const FONT_PATH: &str = "fonts/AlfaSlabOne-Regular.ttf";
+
+#[derive(AssetCollection, Resource)]
+pub struct FontAssets {
+    #[asset(path = "fonts/AlfaSlabOne-Regular.ttf")]
+    pub main: Handle<Font>,
+}
+
+/// Gameplay rules. Until these are loaded the game
+/// plays by the defaults.
+#[derive(AssetCollection, Resource)]
+pub struct RulesAssets {
+    #[asset(path = "scoring.rules.ron")]
+    pub scoring: Handle<ScoringRules>,
+}
+
 // #[derive(AssetCollection)]
 // pub struct AudioAssets {
 //     #[asset(path = "gameover.ogg")]
@@ -46,3 +115,238 @@ pub struct ImageAssets {
 //     #[asset(path = "apple.ogg")]
 //     pub apple:
 // Handle<bevy_kira_audio::AudioSource>, }
+
+/// Run condition for systems that need [`ImageAssets`]
+pub fn images_loaded(
+    images: Option<Res<ImageAssets>>,
+) -> bool {
+    images.is_some()
+}
+
+/// Run condition for systems that need [`FontAssets`]
+pub fn fonts_loaded(
+    fonts: Option<Res<FontAssets>>,
+) -> bool {
+    fonts.is_some()
+}
+
+/// Every asset the loading state is waiting on. The
+/// asset loader keeps its own handles to itself, these
+/// point at the same assets.
+#[derive(Resource, Debug, Default)]
+pub struct LoadingAssets(pub Vec<HandleUntyped>);
+
+impl LoadingAssets {
+    /// How many of the assets have finished loading, and
+    /// how many there are
+    pub fn progress(
+        &self,
+        asset_server: &AssetServer,
+    ) -> (usize, usize) {
+        let loaded = self
+            .0
+            .iter()
+            .filter(|handle| {
+                asset_server.get_load_state(*handle)
+                    == LoadState::Loaded
+            })
+            .count();
+        (loaded, self.0.len())
+    }
+
+    /// Paths of the assets that failed to load
+    pub fn failed(
+        &self,
+        asset_server: &AssetServer,
+    ) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|handle| {
+                asset_server.get_load_state(*handle)
+                    == LoadState::Failed
+            })
+            .map(|handle| {
+                asset_server
+                    .get_handle_path(handle)
+                    .map_or_else(
+                        || format!("{:?}", handle.id()),
+                        |path| {
+                            path.path()
+                                .display()
+                                .to_string()
+                        },
+                    )
+            })
+            .collect()
+    }
+}
+
+/// Asks for the same assets the loading state does,
+/// getting handles to them. Already loading assets
+/// aren't loaded twice.
+fn track_assets(world: &mut World) {
+    let mut handles = ImageAssets::load(world);
+    handles.extend(FontAssets::load(world));
+    handles.extend(RulesAssets::load(world));
+    world.insert_resource(LoadingAssets(handles));
+}
+
+/// Replace the default rules with the loaded ones
+fn apply_rules(
+    mut commands: Commands,
+    rules: Res<RulesAssets>,
+    scoring_rules: Res<Assets<ScoringRules>>,
+) {
+    if let Some(scoring) = scoring_rules.get(&rules.scoring)
+    {
+        commands.insert_resource(scoring.clone());
+    }
+}
+
+#[derive(Component)]
+struct LoadingBar;
+
+fn loading_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(
+                        Val::Percent(100.0),
+                        Val::Percent(100.0),
+                    ),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    gap: Size::all(Val::Px(20.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(GameState::Loading),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Loading",
+                TextStyle {
+                    // the same font `FontAssets` is
+                    // loading
+                    font: asset_server.load(FONT_PATH),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Px(600.0),
+                            Val::Px(30.0),
+                        ),
+                        ..default()
+                    },
+                    background_color: Color::rgb(
+                        0.15, 0.15, 0.15,
+                    )
+                    .into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(
+                                    Val::Percent(0.0),
+                                    Val::Percent(100.0),
+                                ),
+                                ..default()
+                            },
+                            background_color: Color::rgb(
+                                0.35, 0.75, 0.35,
+                            )
+                            .into(),
+                            ..default()
+                        },
+                        LoadingBar,
+                    ));
+                });
+        });
+}
+
+fn update_loading_bar(
+    asset_server: Res<AssetServer>,
+    loading: Res<LoadingAssets>,
+    mut bars: Query<&mut Style, With<LoadingBar>>,
+) {
+    let (loaded, total) = loading.progress(&asset_server);
+    let percent = if total == 0 {
+        100.0
+    } else {
+        loaded as f32 / total as f32 * 100.0
+    };
+    for mut style in bars.iter_mut() {
+        style.size.width = Val::Percent(percent);
+    }
+}
+
+fn loading_failed_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    loading: Res<LoadingAssets>,
+) {
+    let failed = loading.failed(&asset_server);
+    for path in failed.iter() {
+        error!("failed to load asset {path}");
+    }
+    // `FontAssets` is never inserted when loading
+    // fails, the font may still have loaded on its own
+    let text_style = TextStyle {
+        font: asset_server.load(FONT_PATH),
+        font_size: 30.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(
+                        Val::Percent(100.0),
+                        Val::Percent(100.0),
+                    ),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    gap: Size::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: Color::rgb(
+                    0.3, 0.08, 0.08,
+                )
+                .into(),
+                ..default()
+            },
+            StateScoped(GameState::LoadingFailed),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Some of the game's files couldn't be loaded:",
+                TextStyle {
+                    font_size: 40.0,
+                    ..text_style.clone()
+                },
+            ));
+            for path in failed {
+                parent.spawn(TextBundle::from_section(
+                    path,
+                    text_style.clone(),
+                ));
+            }
+            parent.spawn(TextBundle::from_section(
+                "Check the assets folder next to the game.",
+                text_style.clone(),
+            ));
+        });
+}
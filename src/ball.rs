@@ -0,0 +1,625 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_hanabi::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    assets::ImageAssets,
+    blocks::Block,
+    paddle::Steering,
+    powerups::{ActivePowerups, PowerupExpired},
+    serve::Serving,
+    Ball, Damage, GameRng, GameplaySet, Paddle, Powerup,
+    DESTRUCTIBLE_BLOCK_GROUP,
+};
+use rand::Rng;
+
+pub struct BallPlugin;
+
+impl Plugin for BallPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BallSpeed>()
+            .init_resource::<Multiball>()
+            .init_resource::<Magnet>()
+            .init_resource::<AntiStall>()
+            .add_systems(
+                (
+                    update_speed_multiplier,
+                    magnet_force,
+                    apply_spin,
+                    detect_stalls,
+                    clamp_bounce_angles,
+                    normalize_ball_speed,
+                )
+                    .chain()
+                    .in_set(GameplaySet::React)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                fireball_collisions
+                    .in_set(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    expire_fireballs,
+                    sync_fireball_solver_groups,
+                )
+                    .in_set(GameplaySet::React)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+/// The single source of truth for how fast balls
+/// travel. Anything that changes a ball's direction
+/// should scale the result with [`BallSpeed::current`].
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct BallSpeed {
+    /// speed at the start of the first level
+    pub base: f32,
+    /// added to the base speed for every level after
+    /// the first
+    pub per_level: f32,
+    /// added every time a ball damages a block
+    pub per_hit: f32,
+    /// upper bound on the speed gained from hits
+    pub max_hit_bonus: f32,
+    pub level: usize,
+    pub hits: u32,
+    /// temporary modifier, driven by active powerups
+    pub multiplier: f32,
+}
+
+impl Default for BallSpeed {
+    fn default() -> Self {
+        // a^2 + b^2 = c^2, the original launch
+        // velocity of (100, 400)
+        let base =
+            (100.0_f32 * 100.0 + 400.0 * 400.0).sqrt();
+        BallSpeed {
+            base,
+            per_level: 40.0,
+            per_hit: 2.0,
+            max_hit_bonus: 200.0,
+            level: 1,
+            hits: 0,
+            multiplier: 1.0,
+        }
+    }
+}
+
+impl BallSpeed {
+    pub fn current(&self) -> f32 {
+        let level_bonus = self.per_level
+            * self.level.saturating_sub(1) as f32;
+        let hit_bonus = (self.per_hit * self.hits as f32)
+            .min(self.max_hit_bonus);
+        (self.base + level_bonus + hit_bonus)
+            * self.multiplier
+    }
+    /// expand a direction back out into a full
+    /// magnitude velocity
+    pub fn apply(&self, direction: Vec2) -> Vec2 {
+        direction.normalize_or_zero() * self.current()
+    }
+    pub fn start_level(&mut self, level: usize) {
+        self.level = level;
+        self.hits = 0;
+    }
+    pub fn register_hit(&mut self) {
+        self.hits += 1;
+    }
+}
+
+/// How [`crate::MultiballEvent`] splits the balls in play
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct Multiball {
+    /// new balls created from every ball in play
+    pub count: usize,
+    /// angle in radians between neighbouring balls
+    /// of the fan
+    pub spread: f32,
+    /// no new balls are created past this total
+    pub max_balls: usize,
+}
+
+impl Default for Multiball {
+    fn default() -> Self {
+        Multiball {
+            count: 2,
+            spread: 15_f32.to_radians(),
+            max_balls: 12,
+        }
+    }
+}
+
+impl Multiball {
+    /// Directions for the new balls, fanning out
+    /// alternately either side of `direction`: +spread,
+    /// -spread, +2*spread and so on.
+    pub fn split_directions(
+        &self,
+        direction: Vec2,
+    ) -> impl Iterator<Item = Vec2> + '_ {
+        (0..self.count).map(move |i| {
+            let step = (i / 2 + 1) as f32;
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            Vec2::from_angle(sign * step * self.spread)
+                .rotate(direction)
+        })
+    }
+}
+
+/// Tuning for [`Powerup::Magnet`]
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct Magnet {
+    /// balls further than this from the paddle are
+    /// unaffected
+    pub range: f32,
+    /// how quickly, in radians per second, a ball's
+    /// direction turns toward the paddle
+    pub strength: f32,
+}
+
+impl Default for Magnet {
+    fn default() -> Self {
+        Magnet {
+            range: 450.0,
+            strength: 1.5,
+        }
+    }
+}
+
+/// Bend descending balls toward the paddle. Only the
+/// direction changes, the speed always comes from
+/// [`BallSpeed`].
+#[allow(clippy::type_complexity)]
+fn magnet_force(
+    fixed_time: Res<FixedTime>,
+    active: Res<ActivePowerups>,
+    magnet: Res<Magnet>,
+    speed: Res<BallSpeed>,
+    paddles: Query<&Transform, With<Paddle>>,
+    mut balls: Query<
+        (&Transform, &mut Velocity),
+        (With<Ball>, Without<Paddle>),
+    >,
+) {
+    if !active.is_active(Powerup::Magnet) {
+        return;
+    }
+    let Ok(paddle) = paddles.get_single() else {
+        return;
+    };
+    let max_turn =
+        magnet.strength * fixed_time.period.as_secs_f32();
+    for (transform, mut velocity) in balls.iter_mut() {
+        let to_paddle = (paddle.translation
+            - transform.translation)
+            .truncate();
+        if velocity.linvel.y >= 0.0
+            || to_paddle.length() > magnet.range
+        {
+            continue;
+        }
+        let angle =
+            velocity.linvel.angle_between(to_paddle);
+        let turn = angle.clamp(-max_turn, max_turn);
+        velocity.linvel = speed.apply(
+            Vec2::from_angle(turn).rotate(velocity.linvel),
+        );
+    }
+}
+
+/// Curves a ball's path, in radians per second.
+/// Picked up from a moving paddle, see
+/// [`Steering::spin`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Spin(pub f32);
+
+fn apply_spin(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    steering: Res<Steering>,
+    mut balls: Query<(Entity, &mut Spin, &mut Velocity)>,
+) {
+    let dt = fixed_time.period.as_secs_f32();
+    for (entity, mut spin, mut velocity) in balls.iter_mut()
+    {
+        velocity.linvel = Vec2::from_angle(spin.0 * dt)
+            .rotate(velocity.linvel);
+        spin.0 *= (1.0 - steering.spin_decay * dt).max(0.0);
+        if spin.0.abs() < 0.01 {
+            commands.entity(entity).remove::<Spin>();
+        }
+    }
+}
+
+/// Keeps balls out of loops the player can't
+/// influence
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct AntiStall {
+    /// after a bounce, a ball's direction is kept at
+    /// least this many radians away from horizontal
+    pub min_vertical_angle: f32,
+    /// bounces without touching the paddle or a
+    /// destructible block before a ball is nudged
+    pub max_bounces: u32,
+    /// time without touching the paddle or a
+    /// destructible block before a ball is nudged
+    pub max_time: Duration,
+    /// how far, in radians, a stalled ball's direction
+    /// is turned
+    pub nudge: f32,
+}
+
+impl Default for AntiStall {
+    fn default() -> Self {
+        AntiStall {
+            min_vertical_angle: 15_f32.to_radians(),
+            max_bounces: 10,
+            max_time: Duration::from_secs(15),
+            nudge: 20_f32.to_radians(),
+        }
+    }
+}
+
+impl AntiStall {
+    /// Turn `direction` just far enough that it makes
+    /// at least [`AntiStall::min_vertical_angle`] with
+    /// the horizontal. A perfectly flat direction is
+    /// sent down toward the paddle.
+    pub fn clamp_direction(&self, direction: Vec2) -> Vec2 {
+        let direction = direction.normalize_or_zero();
+        let min_y = self.min_vertical_angle.sin();
+        if direction == Vec2::ZERO
+            || direction.y.abs() >= min_y
+        {
+            return direction;
+        }
+        let y =
+            if direction.y > 0.0 { min_y } else { -min_y };
+        let x = (1.0 - min_y * min_y).sqrt()
+            * direction.x.signum();
+        Vec2::new(x, y)
+    }
+}
+
+/// Counts how long a ball has gone without making
+/// progress, see [`AntiStall`]
+#[derive(Component, Debug, Default)]
+pub struct StallDetector {
+    pub bounces: u32,
+    pub elapsed: Duration,
+}
+
+/// Ids of the balls in every collision that started
+/// this tick, paired with the other entity
+pub(crate) fn ball_contacts<'a>(
+    events: &'a mut EventReader<CollisionEvent>,
+    balls: &'a Query<(), With<Ball>>,
+) -> impl Iterator<Item = (Entity, Entity)> + 'a {
+    events.iter().filter_map(|event| {
+        let CollisionEvent::Started(a, b, _) = event else {
+            return None;
+        };
+        if balls.contains(*a) {
+            Some((*a, *b))
+        } else if balls.contains(*b) {
+            Some((*b, *a))
+        } else {
+            None
+        }
+    })
+}
+
+fn clamp_bounce_angles(
+    mut events: EventReader<CollisionEvent>,
+    anti_stall: Res<AntiStall>,
+    ball_ids: Query<(), With<Ball>>,
+    mut balls: Query<&mut Velocity, With<Ball>>,
+) {
+    for (ball, _) in ball_contacts(&mut events, &ball_ids) {
+        let Ok(mut velocity) = balls.get_mut(ball) else {
+            continue;
+        };
+        let clamped =
+            anti_stall.clamp_direction(velocity.linvel);
+        if clamped != velocity.linvel.normalize_or_zero() {
+            velocity.linvel =
+                clamped * velocity.linvel.length();
+        }
+    }
+}
+
+/// Nudge balls that keep bouncing around without
+/// touching the paddle or a block that can break
+#[allow(clippy::type_complexity)]
+fn detect_stalls(
+    fixed_time: Res<FixedTime>,
+    mut events: EventReader<CollisionEvent>,
+    anti_stall: Res<AntiStall>,
+    mut rng: ResMut<GameRng>,
+    ball_ids: Query<(), With<Ball>>,
+    progress: Query<
+        Option<&Block>,
+        Or<(With<Paddle>, With<Block>)>,
+    >,
+    mut balls: Query<
+        (&mut StallDetector, &mut Velocity),
+        (With<Ball>, Without<Serving>),
+    >,
+) {
+    for (ball, other) in
+        ball_contacts(&mut events, &ball_ids)
+    {
+        let Ok((mut detector, _)) = balls.get_mut(ball)
+        else {
+            continue;
+        };
+        match progress.get(other) {
+            Ok(None) => {
+                *detector = StallDetector::default()
+            }
+            Ok(Some(block))
+                if !matches!(block, Block::Gold) =>
+            {
+                *detector = StallDetector::default()
+            }
+            _ => detector.bounces += 1,
+        }
+    }
+    for (mut detector, mut velocity) in balls.iter_mut() {
+        detector.elapsed += fixed_time.period;
+        if detector.bounces < anti_stall.max_bounces
+            && detector.elapsed < anti_stall.max_time
+        {
+            continue;
+        }
+        let side = if rng.gen() { 1.0 } else { -1.0 };
+        let nudged =
+            Vec2::from_angle(side * anti_stall.nudge)
+                .rotate(velocity.linvel);
+        velocity.linvel = anti_stall
+            .clamp_direction(nudged)
+            * velocity.linvel.length();
+        *detector = StallDetector::default();
+    }
+}
+
+fn update_speed_multiplier(
+    active: Res<ActivePowerups>,
+    mut speed: ResMut<BallSpeed>,
+) {
+    if active.is_changed() {
+        let multiplier = active
+            .iter()
+            .filter_map(|active| {
+                active.powerup.speed_multiplier()
+            })
+            .product();
+        if speed.multiplier != multiplier {
+            speed.multiplier = multiplier;
+        }
+    }
+}
+
+fn normalize_ball_speed(
+    speed: Res<BallSpeed>,
+    mut balls: Query<&mut Velocity, With<Ball>>,
+) {
+    let current = speed.current();
+    for mut velocity in balls.iter_mut() {
+        let length = velocity.linvel.length();
+        // a ball at rest stays at rest
+        if length > 0.0 && (length - current).abs() > 0.01 {
+            velocity.linvel = speed.apply(velocity.linvel);
+        }
+    }
+}
+
+/// Draw newly spawned balls
+pub(crate) fn add_ball_shapes(
+    mut commands: Commands,
+    balls: Query<(Entity, &Transform), Added<Ball>>,
+) {
+    let shape = shapes::Circle {
+        radius: 10.0,
+        ..Default::default()
+    };
+    for (entity, transform) in balls.iter() {
+        commands.entity(entity).insert((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shape),
+                transform: *transform,
+                ..default()
+            },
+            Fill::color(Color::WHITE),
+            Stroke::new(Color::BLACK, 1.0),
+        ));
+    }
+}
+
+/// Balls with this component pass straight through
+/// destructible blocks, destroying them instantly.
+/// They still bounce off of gold blocks, walls and the
+/// paddle.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Fireball;
+
+/// Children added to a ball while it is a fireball
+#[derive(Component)]
+pub(crate) struct FireballVisual;
+
+#[derive(Resource)]
+pub(crate) struct FireballTrail(Handle<EffectAsset>);
+
+fn fireball_collisions(
+    mut events: EventReader<CollisionEvent>,
+    fireballs: Query<(), (With<Ball>, With<Fireball>)>,
+    mut blocks: Query<(&Block, &mut Damage)>,
+) {
+    for event in events.iter() {
+        if let CollisionEvent::Started(a, b, _) = event {
+            let block = if fireballs.contains(*a) {
+                *b
+            } else if fireballs.contains(*b) {
+                *a
+            } else {
+                continue;
+            };
+            if let Ok((block, mut damage)) =
+                blocks.get_mut(block)
+            {
+                if !matches!(block, Block::Gold) {
+                    damage.0 = u8::MAX;
+                }
+            }
+        }
+    }
+}
+
+fn expire_fireballs(
+    mut commands: Commands,
+    mut events: EventReader<PowerupExpired>,
+    fireballs: Query<Entity, With<Fireball>>,
+) {
+    for PowerupExpired(powerup) in events.iter() {
+        if *powerup == Powerup::Fireball {
+            for entity in fireballs.iter() {
+                commands
+                    .entity(entity)
+                    .remove::<Fireball>();
+            }
+        }
+    }
+}
+
+fn sync_fireball_solver_groups(
+    mut commands: Commands,
+    added: Query<Entity, Added<Fireball>>,
+    mut removed: RemovedComponents<Fireball>,
+) {
+    for entity in added.iter() {
+        commands.entity(entity).insert(SolverGroups::new(
+            Group::ALL,
+            !DESTRUCTIBLE_BLOCK_GROUP,
+        ));
+    }
+    for entity in removed.iter() {
+        if let Some(mut entity) =
+            commands.get_entity(entity)
+        {
+            entity.remove::<SolverGroups>();
+        }
+    }
+}
+
+pub(crate) fn setup_fireball_trail(
+    mut commands: Commands,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    images: Res<ImageAssets>,
+) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(1.0, 0.7, 0.2, 0.9));
+    gradient.add_key(0.5, Vec4::new(1.0, 0.3, 0.0, 0.6));
+    gradient.add_key(1.0, Vec4::new(0.6, 0.0, 0.0, 0.0));
+
+    let mut size_gradient = Gradient::new();
+    size_gradient.add_key(0.0, Vec2::splat(40.0));
+    size_gradient.add_key(1.0, Vec2::splat(8.0));
+
+    let effect = effects.add(
+        EffectAsset {
+            name: "FireballTrail".into(),
+            capacity: 4096,
+            spawner: Spawner::rate(60.0.into()),
+            ..Default::default()
+        }
+        .init(InitPositionSphereModifier {
+            center: Vec3::ZERO,
+            radius: 6.0,
+            dimension: ShapeDimension::Volume,
+        })
+        .init(InitVelocitySphereModifier {
+            center: Vec3::ZERO,
+            speed: 15.0.into(),
+        })
+        .init(InitLifetimeModifier {
+            lifetime: 0.4.into(),
+        })
+        .render(ParticleTextureModifier {
+            texture: images.fire_trail.clone(),
+        })
+        .render(SizeOverLifetimeModifier {
+            gradient: size_gradient,
+        })
+        .render(ColorOverLifetimeModifier { gradient }),
+    );
+    commands.insert_resource(FireballTrail(effect));
+}
+
+pub(crate) fn add_fireball_visuals(
+    mut commands: Commands,
+    mut fireballs: Query<
+        (Entity, &mut Fill),
+        Added<Fireball>,
+    >,
+    images: Res<ImageAssets>,
+    trail: Res<FireballTrail>,
+) {
+    for (entity, mut fill) in fireballs.iter_mut() {
+        *fill = Fill::color(Color::ORANGE);
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(
+                            1.0, 0.45, 0.1, 0.9,
+                        ),
+                        custom_size: Some(Vec2::splat(
+                            70.0,
+                        )),
+                        ..default()
+                    },
+                    texture: images.fire_glow.clone(),
+                    // behind the ball itself
+                    transform: Transform::from_xyz(
+                        0.0, 0.0, -1.0,
+                    ),
+                    ..default()
+                },
+                FireballVisual,
+            ));
+            parent.spawn((
+                ParticleEffectBundle::new(trail.0.clone()),
+                FireballVisual,
+            ));
+        });
+    }
+}
+
+pub(crate) fn remove_fireball_visuals(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Fireball>,
+    mut balls: Query<(&mut Fill, &Children)>,
+    visuals: Query<(), With<FireballVisual>>,
+) {
+    for entity in removed.iter() {
+        // despawned balls take their children with them
+        let Ok((mut fill, children)) =
+            balls.get_mut(entity)
+        else {
+            continue;
+        };
+        *fill = Fill::color(Color::WHITE);
+        for child in children.iter() {
+            if visuals.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+    }
+}
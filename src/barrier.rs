@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    board::Board,
+    paddle::{paddle_y, PADDLE_HEIGHT},
+    powerups::{
+        tick_active_powerups, ActivePowerups,
+        PowerupExpired,
+    },
+    Ball, GameplaySet, Powerup,
+};
+
+pub const BARRIER_THICKNESS: f32 = 10.0;
+
+/// Height of the barrier's top edge, just under the
+/// paddle's lowest point so their colliders never
+/// overlap. The despawn area starts below the barrier.
+pub fn barrier_top(board: &Board) -> f32 {
+    paddle_y(board) - PADDLE_HEIGHT / 2.0 - 1.0
+}
+
+pub struct BarrierPlugin;
+
+impl Plugin for BarrierPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BarrierSettings>()
+            .add_system(
+                barrier_collisions
+                    .after(tick_active_powerups)
+                    .in_set(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                expire_barrier
+                    .in_set(GameplaySet::React)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct BarrierSettings {
+    /// how many balls the barrier bounces back before
+    /// it breaks. It also disappears when
+    /// [`Powerup::Barrier`] runs out.
+    pub hits: u32,
+}
+
+impl Default for BarrierSettings {
+    fn default() -> Self {
+        BarrierSettings { hits: 1 }
+    }
+}
+
+/// A temporary floor sitting on top of the
+/// [`crate::DespawnArea`]
+#[derive(Component, Debug)]
+pub struct Barrier {
+    pub hits_remaining: u32,
+}
+
+fn barrier_collisions(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    balls: Query<(), With<Ball>>,
+    mut barriers: Query<(
+        Entity,
+        &mut Barrier,
+        &mut Sprite,
+    )>,
+    mut active: ResMut<ActivePowerups>,
+    settings: Res<BarrierSettings>,
+) {
+    for event in events.iter() {
+        let CollisionEvent::Started(a, b, _) = event else {
+            continue;
+        };
+        let barrier = if balls.contains(*a) {
+            *b
+        } else if balls.contains(*b) {
+            *a
+        } else {
+            continue;
+        };
+        let Ok((entity, mut barrier, mut sprite)) =
+            barriers.get_mut(barrier)
+        else {
+            continue;
+        };
+        barrier.hits_remaining =
+            barrier.hits_remaining.saturating_sub(1);
+        if barrier.hits_remaining == 0 {
+            commands.entity(entity).despawn_recursive();
+            active.deactivate(Powerup::Barrier);
+        } else {
+            // fade out as the barrier wears down
+            sprite.color.set_a(
+                0.3 + 0.5 * barrier.hits_remaining as f32
+                    / settings.hits as f32,
+            );
+        }
+    }
+}
+
+fn expire_barrier(
+    mut commands: Commands,
+    mut events: EventReader<PowerupExpired>,
+    barriers: Query<Entity, With<Barrier>>,
+) {
+    for PowerupExpired(powerup) in events.iter() {
+        if *powerup == Powerup::Barrier {
+            for entity in barriers.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
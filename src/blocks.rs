@@ -1,10 +1,26 @@
-use crate::{custom_commands::SpawnPowerup, Damage};
+use crate::{
+    custom_commands::SpawnPowerup, Damage, Difficulty,
+    GameRng, GameState, Powerup, DESTRUCTIBLE_BLOCK_GROUP,
+};
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use Block::*;
 
-#[derive(Clone, Copy, Component)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Component,
+    Serialize,
+    Deserialize,
+)]
 pub enum Block {
     White,
     Orange,
@@ -34,13 +50,37 @@ impl Block {
             Gold => Color::GOLD,
         }
     }
+    /// Gold blocks stay for the whole level and don't
+    /// need breaking to win
+    pub fn is_destructible(&self) -> bool {
+        !matches!(self, Gold)
+    }
+    /// Gold keeps the default solver groups so
+    /// fireballs still bounce off of it
+    pub fn solver_groups(&self) -> SolverGroups {
+        match self {
+            Gold => SolverGroups::default(),
+            _ => SolverGroups::new(
+                DESTRUCTIBLE_BLOCK_GROUP,
+                Group::ALL,
+            ),
+        }
+    }
+}
+
+/// Sent when a block breaks
+pub struct BlockDestroyed {
+    pub block: Block,
+    pub position: Vec3,
 }
 
 pub fn block_removal(
     mut commands: Commands,
     blocks: Query<(Entity, &Transform, &Damage, &Block)>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
+    mut destroyed: EventWriter<BlockDestroyed>,
 ) {
-    let mut rng = rand::thread_rng();
     for (entity, position, damage, block) in blocks.iter() {
         match block {
             Silver => {
@@ -48,6 +88,10 @@ pub fn block_removal(
                     commands
                         .entity(entity)
                         .despawn_recursive();
+                    destroyed.send(BlockDestroyed {
+                        block: *block,
+                        position: position.translation,
+                    });
                 }
             }
             Gold => {
@@ -59,9 +103,17 @@ pub fn block_removal(
                     commands
                         .entity(entity)
                         .despawn_recursive();
+                    destroyed.send(BlockDestroyed {
+                        block: *block,
+                        position: position.translation,
+                    });
                     if rng.gen_range(0..10) == 5 {
                         commands.add(SpawnPowerup {
-                            transform: position.clone(),
+                            transform: *position,
+                            powerup: Powerup::random(
+                                &mut rng.0,
+                                *difficulty,
+                            ),
                         });
                     };
                 }
@@ -69,3 +121,15 @@ pub fn block_removal(
         }
     }
 }
+
+/// The level is won once only gold blocks are left.
+/// Blocks broken this tick are still around, so this
+/// sees them gone on the next one.
+pub fn check_victory(
+    blocks: Query<&Block>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !blocks.iter().any(Block::is_destructible) {
+        next_state.set(GameState::Victory);
+    }
+}
@@ -60,6 +60,7 @@ impl Board {
     }
 }
 
+#[allow(dead_code)]
 #[derive(
     Debug, PartialEq, Copy, Clone, Eq, Hash, Component,
 )]
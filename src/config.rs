@@ -0,0 +1,45 @@
+//! Where settings and saved files are kept on disk
+use std::{env, path::PathBuf};
+
+const APP_DIR: &str = "block_breaker";
+
+/// The per-user config directory for the game, e.g.
+/// `~/.config/block_breaker`. `None` if the platform
+/// doesn't tell us where that is.
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// The per-user directory for files the game writes
+/// itself, like replays, e.g.
+/// `~/.local/share/block_breaker`
+pub fn data_dir() -> Option<PathBuf> {
+    app_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// The game's directory under a per-user base
+/// directory. Windows and macOS use the same base for
+/// everything; elsewhere it's `xdg_var`, falling back
+/// to `home_fallback` under the home directory.
+fn app_dir(
+    xdg_var: &str,
+    home_fallback: &str,
+) -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library/Application Support")
+        })
+    } else {
+        env::var_os(xdg_var).map(PathBuf::from).or_else(
+            || {
+                env::var_os("HOME").map(|home| {
+                    PathBuf::from(home).join(home_fallback)
+                })
+            },
+        )
+    };
+    base.map(|base| base.join(APP_DIR))
+}
@@ -1,58 +1,44 @@
-use bevy::{
-    ecs::system::Command, prelude::*,
-    sprite::MaterialMesh2dBundle,
-};
-use bevy_prototype_lyon::prelude::*;
+use bevy::{ecs::system::Command, prelude::*};
 use bevy_rapier2d::prelude::*;
 
 use crate::{
+    ball::{BallSpeed, Fireball, StallDetector},
+    barrier::{
+        barrier_top, Barrier, BarrierSettings,
+        BARRIER_THICKNESS,
+    },
+    blocks::Block,
     board::{self, Board},
-    levels::LEVEL_1,
-    Ball, Damage, Powerup,
+    gameplay::CurrentLevel,
+    levels,
+    physics::InterpolatedTranslation,
+    powerups::ActivePowerups,
+    scoring::Combo,
+    serve::{serve_position, ServeSettings, Serving},
+    Ball, ConnectToPaddle, Damage, GameInProgress,
+    GameState, Paddle, Powerup, StateScoped, BALL_GROUP,
+    BARRIER_GROUP,
 };
 
 pub struct SpawnBall {
     pub velocity: Velocity,
     pub transform: Transform,
+    pub fireball: bool,
 }
 
 impl Command for SpawnBall {
     fn write(self, world: &mut World) {
-        let shape = shapes::Circle {
-            radius: 10.0,
-            ..Default::default()
-        };
+        self.spawn(world);
+    }
+}
 
+impl SpawnBall {
+    /// Spawn the ball right away, returning its id
+    pub fn spawn(self, world: &mut World) -> Entity {
         let ball_id = world
-            .spawn((
-                ShapeBundle {
-                    path: GeometryBuilder::build_as(&shape),
-                    transform: self.transform,
-                    ..default()
-                },
-                Fill::color(Color::WHITE),
-                Stroke::new(Color::BLACK, 1.0),
+            .spawn(SpatialBundle::from_transform(
+                self.transform,
             ))
-            // material mesh bundle is only applicable in
-            // bevy
-            // 0.8.0 .spawn_bundle(MaterialMesh2dBundle
-            // {     mesh: meshes
-            //         .add(
-            //
-            // bevy::prelude::shape::Circle::new(50.)
-            //                 .into(),
-            //         )
-            //         .into(),
-            //     material: materials
-            //         .add(ColorMaterial::from(Color::
-            // PURPLE)),     transform:
-            // Transform::from_xyz(
-            //         board.physical.x / 2.0,
-            //         50.0,
-            //         0.0,
-            //     ),
-            //     ..default()
-            // })
             .insert(RigidBody::Dynamic)
             .insert(Restitution {
                 coefficient: 1.0,
@@ -63,86 +49,159 @@ impl Command for SpawnBall {
                 combine_rule: CoefficientCombineRule::Min,
             })
             .insert(Collider::ball(10.0))
+            // fast balls would otherwise pass straight
+            // through blocks and the paddle
+            .insert(Ccd::enabled())
             .insert(self.velocity)
+            .insert(InterpolatedTranslation::new(
+                self.transform.translation,
+            ))
             .insert(Ball)
+            .insert(StateScoped(GameState::Playing))
+            .insert(StallDetector::default())
             .insert(LockedAxes::ROTATION_LOCKED)
             .insert(ActiveEvents::COLLISION_EVENTS)
             .insert(GravityScale(0.0))
+            .insert(CollisionGroups::new(
+                BALL_GROUP,
+                Group::ALL,
+            ))
             .id();
-        dbg!(ball_id);
+        if self.fireball {
+            world.entity_mut(ball_id).insert(Fireball);
+        }
+        ball_id
+    }
+}
+
+/// Place a new ball on the paddle, waiting to be
+/// launched. See [`crate::serve`].
+pub struct ServeBall;
+
+impl Command for ServeBall {
+    fn write(self, world: &mut World) {
+        let Some(paddle) = world
+            .query_filtered::<&Transform, With<Paddle>>()
+            .iter(world)
+            .next()
+            .copied()
+        else {
+            warn!("no paddle to serve from");
+            return;
+        };
+        let settings = world.resource::<ServeSettings>();
+        let connection = ConnectToPaddle { diff: 0.0 };
+        let serving = Serving::new(settings);
+        let ball = SpawnBall {
+            velocity: Velocity::zero(),
+            transform: Transform::from_translation(
+                serve_position(&paddle, &connection),
+            ),
+            fireball: false,
+        }
+        .spawn(world);
+        world
+            .entity_mut(ball)
+            .insert((connection, serving));
     }
 }
 
 pub struct SpawnPowerup {
     pub transform: Transform,
+    pub powerup: Powerup,
 }
 
 impl Command for SpawnPowerup {
     fn write(self, world: &mut World) {
-        let capsule = {
-            let mut meshes = world
-                .get_resource_mut::<Assets<Mesh>>()
-                .unwrap();
-            meshes
-                .add(
-                    shape::Capsule {
-                        radius: 10.0,
-                        // rings: todo!(),
-                        depth: 40.0,
-                        // latitudes: todo!(),
-                        // longitudes: todo!(),
-                        // uv_profile: todo!(),
-                        ..Default::default()
-                    }
-                    .into(),
-                )
-                .into()
-        };
-        let color_material = {
-            let mut materials = world
-                .get_resource_mut::<Assets<ColorMaterial>>()
-                .unwrap();
-            materials.add(ColorMaterial::from(
-                Color::ANTIQUE_WHITE,
-            ))
-        };
+        world.spawn((
+            SpatialBundle::from_transform(self.transform),
+            Sensor,
+            Restitution {
+                coefficient: 1.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            }, // ,
+            //     ColliderMassProperties::Density,
+            // )
+            Collider::capsule_y(20.0, 10.0),
+            Velocity::linear(Vec2::new(0.0, -400.0)),
+            InterpolatedTranslation::new(
+                self.transform.translation,
+            ),
+            LockedAxes::ROTATION_LOCKED,
+            self.powerup,
+            ActiveEvents::COLLISION_EVENTS,
+            StateScoped(GameState::Playing),
+        ));
+    }
+}
 
-        let powerup_id = world
-            .spawn((
-                MaterialMesh2dBundle {
-                    mesh: capsule,
-                    material: color_material,
-                    transform: self.transform,
-                    // .clone()
-                    // .with_rotation(Quat::from_rotation_z(
-                    //     std::f32::consts::FRAC_PI_2,
-                    // )),
-                    ..default()
-                },
-                Sensor,
-                Restitution {
-                    coefficient: 1.0,
-                    combine_rule:
-                        CoefficientCombineRule::Min,
+/// Spawn a [`Barrier`], or restore the hits of the one
+/// already in play
+pub struct SpawnBarrier;
+
+impl Command for SpawnBarrier {
+    fn write(self, world: &mut World) {
+        let hits = world.resource::<BarrierSettings>().hits;
+        let mut barriers =
+            world.query::<(&mut Barrier, &mut Sprite)>();
+        if let Some((mut barrier, mut sprite)) =
+            barriers.iter_mut(world).next()
+        {
+            barrier.hits_remaining = hits;
+            sprite.color.set_a(0.8);
+            return;
+        }
+
+        let board =
+            world.get_resource::<Board>().unwrap().clone();
+        world.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(
+                        0.18, 0.71, 0.63, 0.8,
+                    ),
+                    custom_size: Some(Vec2::new(
+                        board.physical.x,
+                        BARRIER_THICKNESS,
+                    )),
+                    ..Default::default()
                 },
-                Friction {
-                    coefficient: 0.0,
-                    combine_rule:
-                        CoefficientCombineRule::Min,
-                }, // ,
-                //     ColliderMassProperties::Density,
-                // )
-                Collider::capsule_y(20.0, 10.0),
-                Velocity::linear(Vec2::new(0.0, -400.0)),
-                LockedAxes::ROTATION_LOCKED,
-                Powerup::TripleBall,
-                ActiveEvents::COLLISION_EVENTS,
-            ))
-            .id();
-        dbg!(powerup_id);
+                transform: Transform::from_xyz(
+                    board.physical.x / 2.0,
+                    barrier_top(&board)
+                        - BARRIER_THICKNESS / 2.0,
+                    4.5,
+                ),
+                ..Default::default()
+            },
+            RigidBody::Fixed,
+            Collider::cuboid(
+                board.physical.x / 2.0,
+                BARRIER_THICKNESS / 2.0,
+            ),
+            CollisionGroups::new(BARRIER_GROUP, BALL_GROUP),
+            Restitution {
+                coefficient: 1.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            ActiveEvents::COLLISION_EVENTS,
+            Barrier {
+                hits_remaining: hits,
+            },
+            StateScoped(GameState::Playing),
+        ));
     }
 }
 
+/// Spawn a level's blocks and serve its first ball
 pub struct SpawnLevel {
     pub level: usize,
 }
@@ -151,7 +210,16 @@ impl Command for SpawnLevel {
     fn write(self, world: &mut World) {
         let board =
             world.get_resource::<Board>().unwrap().clone();
-        for (row_index, row) in LEVEL_1.iter().enumerate() {
+        world.insert_resource(CurrentLevel(self.level));
+        world
+            .resource_mut::<BallSpeed>()
+            .start_level(self.level);
+        for (row_index, row) in
+            levels::level(self.level)
+                .blocks
+                .iter()
+                .enumerate()
+        {
             for (column_index, column) in
                 row.iter().enumerate()
             {
@@ -198,9 +266,84 @@ impl Command for SpawnLevel {
                         }
                         ,*block
                         ,Damage(0)
+                        ,block.solver_groups()
+                        ,StateScoped(GameState::Playing)
                     ));
                 }
             }
         }
+        ServeBall.write(world);
+    }
+}
+
+/// Throw away the game in progress, so the next time
+/// [`GameState::Playing`] is entered a new one starts
+pub struct EndGame;
+
+impl Command for EndGame {
+    fn write(self, world: &mut World) {
+        let entities: Vec<Entity> = world
+            .query::<(Entity, &StateScoped)>()
+            .iter(world)
+            .filter(|(_, scope)| {
+                scope.0 == GameState::Playing
+            })
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in entities {
+            if let Some(entity) =
+                world.get_entity_mut(entity)
+            {
+                entity.despawn_recursive();
+            }
+        }
+        *world.resource_mut::<ActivePowerups>() =
+            ActivePowerups::default();
+        world.resource_mut::<GameInProgress>().0 = false;
+    }
+}
+
+/// Put the level back the way it started and serve
+/// again, keeping the score, lives and run stats
+pub struct RestartLevel;
+
+impl Command for RestartLevel {
+    fn write(self, world: &mut World) {
+        let entities: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(
+                With<Block>,
+                With<Ball>,
+                With<Powerup>,
+                With<Barrier>,
+            )>>()
+            .iter(world)
+            .collect();
+        for entity in entities {
+            if let Some(entity) =
+                world.get_entity_mut(entity)
+            {
+                entity.despawn_recursive();
+            }
+        }
+        *world.resource_mut::<ActivePowerups>() =
+            ActivePowerups::default();
+        *world.resource_mut::<Combo>() = Combo::default();
+        let level = world.resource::<CurrentLevel>().0;
+        SpawnLevel { level }.write(world);
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+    }
+}
+
+/// Throw away the game in progress and start a new one
+pub struct RestartGame;
+
+impl Command for RestartGame {
+    fn write(self, world: &mut World) {
+        EndGame.write(world);
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
     }
 }
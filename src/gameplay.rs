@@ -0,0 +1,674 @@
+//! Everything that decides how a game plays out, with
+//! no rendering. [`GameplayPlugin`] runs under
+//! [`MinimalPlugins`] and, given the same seed and
+//! input, plays out identically every time.
+use std::time::Instant;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    ball::{
+        BallPlugin, BallSpeed, Fireball, Multiball, Spin,
+    },
+    barrier::{
+        barrier_top, BarrierPlugin, BARRIER_THICKNESS,
+    },
+    blocks::{
+        block_removal, check_victory, Block, BlockDestroyed,
+    },
+    board::Board,
+    custom_commands::*,
+    game_in_progress,
+    input::{PlayerInput, PlayerInputPlugin},
+    paddle::{
+        paddle_width, paddle_y, track_paddle_velocity,
+        PaddlePlugin, PaddleVelocity, Steering,
+        PADDLE_COLOR, PADDLE_HEIGHT, PADDLE_WIDTH,
+    },
+    physics::{InterpolatedTranslation, PhysicsPlugin},
+    powerups::{
+        tick_active_powerups, ActivePowerups,
+        PowerupCollected, PowerupPlugin,
+    },
+    replay::{Playback, ReplayPlugin},
+    scoring::{ScorePlugin, ScoringRules},
+    serve::{ServePlugin, Serving},
+    starting_new_game, Ball, Damage, DespawnArea,
+    Difficulty, GameInProgress, GameRng, GameSeed,
+    GameState, GameplaySet, Lives, MultiballEvent, Paddle,
+    PlayingAreaBorder, Powerup, SeedSource, StateScoped,
+    BARRIER_GROUP,
+};
+
+pub struct GameplayPlugin {
+    /// seeds the [`GameRng`] at the start of every game
+    pub seed: SeedSource,
+}
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .insert_resource(Board::new(11, 28))
+            .insert_resource(self.seed)
+            // both replaced by `pick_seed` when a game
+            // starts
+            .insert_resource(GameSeed(0))
+            .insert_resource(GameRng::from_seed(GameSeed(
+                0,
+            )))
+            .add_plugin(PhysicsPlugin)
+            .add_plugin(PlayerInputPlugin)
+            .add_plugin(ScorePlugin)
+            .add_plugin(PowerupPlugin)
+            .add_plugin(BallPlugin)
+            .add_plugin(PaddlePlugin)
+            .add_plugin(BarrierPlugin)
+            .add_plugin(ServePlugin)
+            .add_plugin(ReplayPlugin)
+            .init_resource::<Lives>()
+            .init_resource::<StartingLevel>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<GameInProgress>()
+            .init_resource::<Difficulty>()
+            .add_event::<MultiballEvent>()
+            .add_event::<BlockDestroyed>()
+            .add_systems(
+                (
+                    despawn_area_collisions,
+                    ball_collisions
+                        .after(track_paddle_velocity),
+                    movement,
+                    track_damage,
+                    powerup_gravity,
+                    powerup_collisions
+                        .after(tick_active_powerups),
+                    apply_powerups
+                        .after(powerup_collisions),
+                )
+                    .in_set(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    block_removal,
+                    check_victory.before(block_removal),
+                    multiball_events,
+                )
+                    .in_set(GameplaySet::React)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    pick_seed,
+                    spawn_new_game.after(pick_seed),
+                )
+                    .distributive_run_if(starting_new_game)
+                    .in_schedule(OnEnter(
+                        GameState::Playing,
+                    )),
+            )
+            // the game is left either paused or over,
+            // and ending it clears this again
+            .add_system(
+                mark_game_in_progress.in_schedule(OnExit(
+                    GameState::Playing,
+                )),
+            )
+            .add_system(
+                end_game
+                    .run_if(game_in_progress)
+                    .in_schedule(OnEnter(GameState::Menu)),
+            )
+            .add_system(
+                despawn_out_of_scope
+                    .in_base_set(CoreSet::StateTransitions)
+                    .after(
+                        apply_state_transition::<GameState>,
+                    ),
+            );
+    }
+}
+
+/// The level every new game starts on
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartingLevel(pub usize);
+
+impl Default for StartingLevel {
+    fn default() -> Self {
+        StartingLevel(1)
+    }
+}
+
+/// The level being played, set by [`SpawnLevel`]
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentLevel(pub usize);
+
+impl Default for CurrentLevel {
+    fn default() -> Self {
+        CurrentLevel(1)
+    }
+}
+
+/// An app running the game with no window or renderer.
+/// It only advances when [`step`] is called.
+pub fn headless_app(seed: u64) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(GameplayPlugin {
+            seed: SeedSource::Fixed(seed),
+        })
+        .insert_resource(
+            ScoringRules::load_shipped()
+                .expect("couldn't read the scoring rules"),
+        )
+        // freeze the clock, ticks come from `step`
+        .insert_resource(
+            TimeUpdateStrategy::ManualInstant(
+                Instant::now(),
+            ),
+        );
+    app
+}
+
+/// Run exactly one fixed tick of a [`headless_app`]
+pub fn step(app: &mut App) {
+    let period = app.world.resource::<FixedTime>().period;
+    app.world.resource_mut::<FixedTime>().tick(period);
+    app.update();
+}
+
+/// Seed the game about to start, before anything
+/// records or uses the seed
+pub(crate) fn pick_seed(
+    source: Res<SeedSource>,
+    playback: Option<Res<Playback>>,
+    mut seed: ResMut<GameSeed>,
+    mut rng: ResMut<GameRng>,
+) {
+    seed.0 = match (playback, *source) {
+        (Some(playback), _) => playback.replay().seed,
+        (None, SeedSource::Fixed(fixed)) => fixed,
+        (None, SeedSource::Random) => rand::random(),
+    };
+    *rng = GameRng::from_seed(*seed);
+}
+
+fn spawn_new_game(
+    mut commands: Commands,
+    board: Res<Board>,
+    level: Res<StartingLevel>,
+    mut lives: ResMut<Lives>,
+) {
+    *lives = Lives::default();
+
+    let paddle_translation = Vec3::new(
+        board.physical.x / 2.0,
+        paddle_y(&board),
+        5.0,
+    );
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: PADDLE_COLOR,
+                custom_size: Some(Vec2::new(
+                    PADDLE_WIDTH,
+                    PADDLE_HEIGHT,
+                )),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(
+                paddle_translation,
+            ),
+            ..Default::default()
+        },
+        InterpolatedTranslation::new(paddle_translation),
+        RigidBody::KinematicPositionBased,
+        KinematicCharacterController {
+            filter_groups: Some(CollisionGroups::new(
+                Group::ALL,
+                !BARRIER_GROUP,
+            )),
+            ..default()
+        },
+        Collider::cuboid(
+            PADDLE_WIDTH / 2.0,
+            PADDLE_HEIGHT / 2.0,
+        ),
+        Paddle,
+        PaddleVelocity::default(),
+        ActiveEvents::COLLISION_EVENTS,
+        StateScoped(GameState::Playing),
+    ));
+
+    commands.spawn((
+        SpatialBundle::default(),
+        RigidBody::Fixed,
+        Restitution {
+            coefficient: 1.0,
+            combine_rule: CoefficientCombineRule::Min,
+        },
+        Friction {
+            coefficient: 0.0,
+            combine_rule: CoefficientCombineRule::Min,
+        },
+        Collider::polyline(
+            vec![
+                Vect::new(0.0, 0.0),
+                Vect::new(board.physical.x, 0.0),
+                Vect::new(
+                    board.physical.x,
+                    board.physical.y,
+                ),
+                Vect::new(0.0, board.physical.y),
+            ],
+            Some(vec![[0, 1], [1, 2], [2, 3], [3, 0]]),
+        ),
+        PlayingAreaBorder,
+        StateScoped(GameState::Playing),
+    ));
+
+    // death area, everything below where the barrier
+    // goes so balls bouncing off the barrier never touch
+    // it
+    let despawn_area_top =
+        barrier_top(&board) - BARRIER_THICKNESS;
+    commands.spawn((
+        SpatialBundle {
+            transform: Transform::from_xyz(
+                board.physical.x / 2.0,
+                despawn_area_top / 2.0,
+                0.0,
+            ),
+            ..default()
+        },
+        Sensor,
+        Collider::cuboid(
+            board.physical.x / 2.0,
+            despawn_area_top / 2.0,
+        ),
+        DespawnArea,
+        StateScoped(GameState::Playing),
+    ));
+
+    commands.add(SpawnLevel { level: level.0 });
+}
+
+fn mark_game_in_progress(
+    mut in_progress: ResMut<GameInProgress>,
+) {
+    in_progress.0 = true;
+}
+
+fn end_game(mut commands: Commands) {
+    commands.add(EndGame);
+}
+
+fn despawn_out_of_scope(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    scoped: Query<(Entity, &StateScoped)>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for (entity, scope) in scoped.iter() {
+        if !state.0.is_within(scope.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn despawn_area_collisions(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    despawn_area: Query<Entity, With<DespawnArea>>,
+    mut ball: Query<Entity, With<Ball>>,
+) {
+    for event in events.iter() {
+        match event {
+            CollisionEvent::Started(a, b, _) => {
+                // info!(?a, ?b, "despawn_event");
+                if let (Ok(entity), Ok(_wall)) =
+                    (ball.get_mut(*a), despawn_area.get(*b))
+                {
+                    commands
+                        .entity(entity)
+                        .despawn_recursive();
+                } else if let (Ok(entity), Ok(_wall)) =
+                    (ball.get_mut(*b), despawn_area.get(*a))
+                {
+                    commands
+                        .entity(entity)
+                        .despawn_recursive();
+                }
+            }
+            CollisionEvent::Stopped(_, _, _) => {}
+        }
+    }
+}
+#[allow(clippy::type_complexity)]
+fn ball_collisions(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    mut balls: Query<
+        (Entity, &mut Velocity, &Transform),
+        (With<Ball>, Without<Paddle>),
+    >,
+    paddles: Query<
+        (Entity, &Transform, &PaddleVelocity),
+        (With<Paddle>, Without<Ball>),
+    >,
+    ball_speed: Res<BallSpeed>,
+    steering: Res<Steering>,
+) {
+    for event in events.iter() {
+        match event {
+            CollisionEvent::Started(a, b, _) => {
+                // info!(?a, ?b, "ball_collision");
+                let mut ball = if let Ok(a) =
+                    balls.get_mut(*a)
+                {
+                    Some(a)
+                } else {
+                    balls.get_mut(*b).ok()
+                };
+
+                let paddle = if let Ok(a) = paddles.get(*a)
+                {
+                    Some(a)
+                } else {
+                    paddles.get(*b).ok()
+                };
+                if let (
+                    Some((
+                        entity,
+                        velocity,
+                        ball_transform,
+                    )),
+                    Some((
+                        _,
+                        paddle_transform,
+                        paddle_velocity,
+                    )),
+                ) = (&mut ball, paddle)
+                {
+                    let x_diff = ball_transform
+                        .translation
+                        .x
+                        - paddle_transform.translation.x;
+
+                    // TODO: Jacob says this `10` might need
+                    // to be a function of the paddle width
+                    let new_velocity =
+                        ball_speed.apply(Vec2::new(
+                            x_diff * steering.offset
+                                + paddle_velocity.x
+                                    * steering.velocity,
+                            velocity.linvel.y,
+                        ));
+
+                    **velocity =
+                        Velocity::linear(Vec2::new(
+                            new_velocity.x,
+                            new_velocity.y.abs(),
+                        ));
+
+                    if steering.spin != 0.0 {
+                        // curve the way the paddle was
+                        // moving
+                        commands.entity(*entity).insert(
+                            Spin(
+                                -paddle_velocity.x
+                                    * steering.spin,
+                            ),
+                        );
+                    }
+                }
+            }
+            CollisionEvent::Stopped(_, _, _) => {}
+        }
+    }
+}
+
+fn track_damage(
+    mut events: EventReader<CollisionEvent>,
+    mut blocks: Query<&mut Damage, With<Block>>,
+    ball: Query<Entity, With<Ball>>,
+    mut ball_speed: ResMut<BallSpeed>,
+) {
+    for event in events.iter() {
+        match event {
+            CollisionEvent::Started(_a, _b, _) => {}
+            CollisionEvent::Stopped(a, b, _) => {
+                if let (Ok(_), Ok(mut block_damage)) =
+                    (ball.get(*a), blocks.get_mut(*b))
+                {
+                    // fireballs max out damage, so
+                    // this can overflow
+                    block_damage.0 =
+                        block_damage.0.saturating_add(1);
+                    ball_speed.register_hit();
+                } else if let (
+                    Ok(_),
+                    Ok(mut block_damage),
+                ) =
+                    (ball.get(*b), blocks.get_mut(*a))
+                {
+                    // fireballs max out damage, so
+                    // this can overflow
+                    block_damage.0 =
+                        block_damage.0.saturating_add(1);
+                    ball_speed.register_hit();
+                }
+            }
+        }
+    }
+}
+
+/// pixels per second
+const PADDLE_SPEED: f32 = 300.0;
+/// share of the distance to [`PlayerInput::target_x`]
+/// the paddle covers every second, smoothing out mouse
+/// movement
+const PADDLE_FOLLOW_RATE: f32 = 15.0;
+fn movement(
+    fixed_time: Res<FixedTime>,
+    input: Res<PlayerInput>,
+    active_powerups: Res<ActivePowerups>,
+    board: Res<Board>,
+    mut controllers: Query<
+        (&Transform, &mut KinematicCharacterController),
+        With<Paddle>,
+    >,
+) {
+    if active_powerups.is_active(Powerup::FreezePaddle) {
+        return;
+    }
+    if input.movement == 0.0 && input.target_x.is_none() {
+        return;
+    }
+    let reversed =
+        active_powerups.is_active(Powerup::ReverseControls);
+    let dt = fixed_time.period.as_secs_f32();
+    // keep the whole paddle on the board
+    let half_width = paddle_width(&active_powerups) / 2.0;
+    for (transform, mut controller) in
+        controllers.iter_mut()
+    {
+        let step = match input.target_x {
+            Some(target_x) => {
+                let target_x = if reversed {
+                    board.physical.x - target_x
+                } else {
+                    target_x
+                }
+                .clamp(
+                    half_width,
+                    board.physical.x - half_width,
+                );
+                (target_x - transform.translation.x)
+                    * (1.0
+                        - (-PADDLE_FOLLOW_RATE * dt).exp())
+            }
+            None => {
+                let speed = if reversed {
+                    -PADDLE_SPEED
+                } else {
+                    PADDLE_SPEED
+                };
+                input.movement * speed * dt
+            }
+        };
+        controller.translation =
+            match controller.translation {
+                Some(mut vector) => {
+                    vector.x = step;
+                    Some(vector)
+                }
+                None => Some(Vec2::new(step, 0.0)),
+            }
+    }
+}
+
+/// pixels per second
+const POWERUP_FALL_SPEED: f32 = 60.0;
+fn powerup_gravity(
+    fixed_time: Res<FixedTime>,
+    mut powerups: Query<&mut Transform, With<Powerup>>,
+) {
+    let fall = POWERUP_FALL_SPEED
+        * fixed_time.period.as_secs_f32();
+    for mut position in powerups.iter_mut() {
+        position.translation.y -= fall;
+    }
+}
+
+fn powerup_collisions(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    powerups: Query<(Entity, &Powerup)>,
+    paddle: Query<Entity, With<Paddle>>,
+    mut collected: EventWriter<PowerupCollected>,
+) {
+    let paddle = paddle.single();
+    for (powerup_sensor, powerup) in powerups.iter() {
+        if rapier_context
+            .intersection_pair(paddle, powerup_sensor)
+            .is_some()
+        {
+            commands
+                .entity(powerup_sensor)
+                .despawn_recursive();
+            collected.send(PowerupCollected(*powerup));
+        }
+    }
+}
+
+fn apply_powerups(
+    mut commands: Commands,
+    mut collected: EventReader<PowerupCollected>,
+    mut multiball: EventWriter<MultiballEvent>,
+    mut active_powerups: ResMut<ActivePowerups>,
+    mut lives: ResMut<Lives>,
+    balls: Query<Entity, With<Ball>>,
+) {
+    for PowerupCollected(powerup) in collected.iter() {
+        match powerup {
+            Powerup::TripleBall => {
+                multiball.send(MultiballEvent);
+            }
+            Powerup::Life => {
+                lives.0 += 1;
+            }
+            // never dropped, see `Powerup::DROPPABLE`
+            Powerup::WidePaddle
+            | Powerup::Gunship
+            | Powerup::Sticky => {
+                warn!(
+                    "{} isn't implemented yet",
+                    powerup.label()
+                );
+            }
+            Powerup::ShrinkPaddle
+            | Powerup::ReverseControls
+            | Powerup::FreezePaddle
+            | Powerup::Magnet => {
+                active_powerups.activate(
+                    *powerup,
+                    powerup.duration().unwrap(),
+                );
+            }
+            Powerup::Barrier => {
+                active_powerups.activate(
+                    *powerup,
+                    powerup.duration().unwrap(),
+                );
+                commands.add(SpawnBarrier);
+            }
+            Powerup::SlowBall | Powerup::FastBall => {
+                // slow and fast cancel each other out
+                // rather than stacking
+                active_powerups
+                    .deactivate(Powerup::SlowBall);
+                active_powerups
+                    .deactivate(Powerup::FastBall);
+                active_powerups.activate(
+                    *powerup,
+                    powerup.duration().unwrap(),
+                );
+            }
+            Powerup::Fireball => {
+                active_powerups.activate(
+                    *powerup,
+                    powerup.duration().unwrap(),
+                );
+                for ball in balls.iter() {
+                    commands.entity(ball).insert(Fireball);
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn multiball_events(
+    mut commands: Commands,
+    mut events: EventReader<MultiballEvent>,
+    all_balls: Query<(), With<Ball>>,
+    // a ball waiting to be served isn't moving, so it
+    // has no direction to split along
+    balls: Query<
+        (&Velocity, &Transform, Option<&Fireball>),
+        (With<Ball>, Without<Serving>),
+    >,
+    ball_speed: Res<BallSpeed>,
+    multiball: Res<Multiball>,
+) {
+    // balls spawned here only exist once the commands
+    // run, so count them as they're queued
+    let mut total = all_balls.iter().len();
+    for _ in events.iter() {
+        'split: for (velocity, transform, fireball) in
+            balls.iter()
+        {
+            for direction in
+                multiball.split_directions(velocity.linvel)
+            {
+                if total >= multiball.max_balls {
+                    break 'split;
+                }
+                commands.add(SpawnBall {
+                    velocity: Velocity::linear(
+                        ball_speed.apply(direction),
+                    ),
+                    transform: *transform,
+                    fireball: fireball.is_some(),
+                });
+                total += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! The best games played on this machine, kept in the
+//! data directory between runs
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{config, Difficulty};
+
+/// Bumped whenever [`HighScoreEntry`] changes in a way
+/// older files can't be read as
+pub const HIGH_SCORES_VERSION: u32 = 1;
+
+/// How many games the table keeps
+pub const MAX_HIGH_SCORES: usize = 10;
+
+/// Loads the table at startup and saves it whenever it
+/// changes. Left out of headless apps so tests never
+/// touch the player's scores.
+pub struct SaveHighScoresPlugin;
+
+impl Plugin for SaveHighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load())
+            .add_system(save_high_scores);
+    }
+}
+
+/// One game in the table. Every field has a default so
+/// entries written by older versions still load.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+#[serde(default)]
+pub struct HighScoreEntry {
+    /// `None` until the player types one in
+    pub name: Option<String>,
+    pub score: u32,
+    pub time: Duration,
+    /// the level the game ended on
+    pub level: usize,
+    /// when the game ended, in seconds since the unix
+    /// epoch
+    pub date: u64,
+    pub mode: Difficulty,
+    pub seed: u64,
+}
+
+impl HighScoreEntry {
+    /// Whether this game beats `other`. A faster time
+    /// breaks a tie.
+    pub fn beats(&self, other: &HighScoreEntry) -> bool {
+        self.score > other.score
+            || self.score == other.score
+                && self.time < other.time
+    }
+
+    /// The date as `YYYY-MM-DD`, in UTC
+    pub fn date_label(&self) -> String {
+        let (year, month, day) =
+            civil_date(self.date / (24 * 60 * 60));
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// Seconds since the unix epoch, for
+/// [`HighScoreEntry::date`]
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Year, month and day of the `days`th day since the
+/// unix epoch
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    // Howard Hinnant's `civil_from_days`, for dates
+    // after 1970
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460
+        + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year = day_of_era
+        - (365 * year_of_era + year_of_era / 4
+            - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    (year + u64::from(month <= 2), month, day)
+}
+
+/// The best games, best first
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, Resource,
+)]
+pub struct HighScores(pub Vec<HighScoreEntry>);
+
+/// What's written to disk
+#[derive(Serialize, Deserialize)]
+struct HighScoresFile {
+    version: u32,
+    entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    pub fn best(&self) -> Option<&HighScoreEntry> {
+        self.0.first()
+    }
+
+    /// Adds `entry` in its place, dropping whatever
+    /// falls off the bottom. Returns its place, or
+    /// `None` if it didn't make the table.
+    pub fn insert(
+        &mut self,
+        entry: HighScoreEntry,
+    ) -> Option<usize> {
+        let rank = self
+            .0
+            .iter()
+            .position(|other| entry.beats(other))
+            .unwrap_or(self.0.len());
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        self.0.insert(rank, entry);
+        self.0.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    /// Where the table is saved
+    pub fn path() -> Option<PathBuf> {
+        config::data_dir()
+            .map(|dir| dir.join("high_scores.ron"))
+    }
+
+    /// The saved table, or an empty one if there is
+    /// none or it can't be read
+    pub fn load() -> Self {
+        HighScores::path()
+            .map_or_else(HighScores::default, |path| {
+                HighScores::load_from(&path)
+            })
+    }
+
+    /// Never fails. A file that can't be read is moved
+    /// aside to `<path>.bad`, so it isn't lost when the
+    /// table is next saved, and the table starts over.
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return HighScores::default();
+        };
+        match HighScores::from_ron(&contents) {
+            Ok(high_scores) => high_scores,
+            Err(error) => {
+                let aside = path.with_extension("ron.bad");
+                warn!(
+                    "ignoring unreadable high scores in {}, moved to {}: {error}",
+                    path.display(),
+                    aside.display()
+                );
+                if let Err(error) = fs::rename(path, &aside)
+                {
+                    warn!("couldn't move high scores: {error}");
+                }
+                HighScores::default()
+            }
+        }
+    }
+
+    pub fn from_ron(
+        contents: &str,
+    ) -> Result<Self, String> {
+        let file: HighScoresFile = ron::from_str(contents)
+            .map_err(|error| error.to_string())?;
+        if file.version != HIGH_SCORES_VERSION {
+            return Err(format!(
+                "high scores are version {}, expected {}",
+                file.version, HIGH_SCORES_VERSION
+            ));
+        }
+        // the file may have been edited, so don't trust
+        // its order or length
+        let mut high_scores = HighScores::default();
+        for entry in file.entries {
+            high_scores.insert(entry);
+        }
+        Ok(high_scores)
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(
+            &HighScoresFile {
+                version: HIGH_SCORES_VERSION,
+                entries: self.0.clone(),
+            },
+            ron::ser::PrettyConfig::default(),
+        )
+        .map_err(|error| error.to_string())
+    }
+
+    /// Writes a temporary file first and renames it
+    /// over the old one, so a crash mid-save can't
+    /// leave half a table behind
+    pub fn save_to(
+        &self,
+        path: &Path,
+    ) -> Result<(), String> {
+        let contents = self.to_ron()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|error| error.to_string())?;
+        }
+        let temporary = path.with_extension("ron.tmp");
+        fs::write(&temporary, contents)
+            .map_err(|error| error.to_string())?;
+        fs::rename(&temporary, path)
+            .map_err(|error| error.to_string())
+    }
+}
+
+fn save_high_scores(high_scores: Res<HighScores>) {
+    if !high_scores.is_changed() || high_scores.is_added() {
+        return;
+    }
+    let Some(path) = HighScores::path() else {
+        return;
+    };
+    if let Err(error) = high_scores.save_to(&path) {
+        warn!("couldn't save high scores: {error}");
+    }
+}
@@ -0,0 +1,310 @@
+//! What the player sees over the board while playing.
+//! Every field is only rewritten when what it shows
+//! changes.
+use std::time::Duration;
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+
+use crate::{
+    assets::{fonts_loaded, FontAssets},
+    gameplay::CurrentLevel,
+    high_scores::HighScores,
+    levels,
+    powerups::ActivePowerups,
+    scoring::{Combo, Score, ScoringRules, Timer},
+    starting_new_game, Ball, GameState, Lives, StateScoped,
+};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            spawn_hud
+                .run_if(starting_new_game)
+                .in_schedule(OnEnter(GameState::Playing)),
+        )
+        .add_systems((
+            update_score.run_if(
+                hud_spawned
+                    .or_else(resource_changed::<Score>())
+                    .or_else(
+                        resource_changed::<HighScores>(),
+                    ),
+            ),
+            update_lives.run_if(
+                hud_spawned
+                    .or_else(resource_changed::<Lives>()),
+            ),
+            update_level.run_if(hud_spawned.or_else(
+                resource_changed::<CurrentLevel>(),
+            )),
+            // the clock only shows whole seconds
+            update_time.run_if(
+                hud_spawned.or_else(on_timer(
+                    Duration::from_secs(1),
+                )),
+            ),
+            update_ball_count,
+            update_powerups.run_if(fonts_loaded).run_if(
+                hud_spawned.or_else(resource_changed::<
+                    ActivePowerups,
+                >()),
+            ),
+            // the rules only exist once loading is done
+            update_multiplier
+                .run_if(resource_exists::<ScoringRules>())
+                .run_if(
+                    hud_spawned.or_else(
+                        resource_changed::<Combo>(),
+                    ),
+                ),
+        ));
+    }
+}
+
+/// One piece of text on the HUD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub enum HudField {
+    Score,
+    HighScore,
+    Lives,
+    Level,
+    Time,
+    Balls,
+    /// one section per active powerup
+    Powerups,
+    /// the combo multiplier, hidden while there is none
+    Multiplier,
+}
+
+impl HudField {
+    const ALL: [HudField; 8] = [
+        HudField::Score,
+        HudField::HighScore,
+        HudField::Lives,
+        HudField::Level,
+        HudField::Time,
+        HudField::Balls,
+        HudField::Powerups,
+        HudField::Multiplier,
+    ];
+}
+
+fn hud_spawned(added: Query<(), Added<HudField>>) -> bool {
+    !added.is_empty()
+}
+
+fn spawn_hud(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+) {
+    let text_style = TextStyle {
+        font: fonts.main.clone(),
+        font_size: 30.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(
+                    Val::Percent(100.0),
+                    Val::Auto,
+                ),
+                position_type: PositionType::Absolute,
+                justify_content:
+                    JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(10.0)),
+                gap: Size::all(Val::Px(20.0)),
+                ..default()
+            },
+            background_color: Color::rgba(
+                0.0, 0.0, 0.0, 0.4,
+            )
+            .into(),
+            ..default()
+        })
+        .insert(StateScoped(GameState::Playing))
+        .with_children(|parent| {
+            for field in HudField::ALL {
+                let style = match field {
+                    HudField::Multiplier => TextStyle {
+                        font_size: 40.0,
+                        color: Color::rgb(1.0, 0.8, 0.2),
+                        ..text_style.clone()
+                    },
+                    _ => text_style.clone(),
+                };
+                parent.spawn((
+                    TextBundle::from_section("", style),
+                    field,
+                ));
+            }
+        });
+}
+
+/// The first section of `field`'s text
+fn set_text(
+    texts: &mut Query<(&mut Text, &HudField)>,
+    field: HudField,
+    value: String,
+) {
+    for (mut text, _) in texts
+        .iter_mut()
+        .filter(|(_, other)| **other == field)
+    {
+        text.sections[0].value = value.clone();
+    }
+}
+
+/// The high score shown is the current score once the
+/// player passes the best saved one
+fn update_score(
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    mut texts: Query<(&mut Text, &HudField)>,
+) {
+    let best = high_scores
+        .best()
+        .map_or(0, |entry| entry.score)
+        .max(score.score);
+    set_text(
+        &mut texts,
+        HudField::Score,
+        format!("Score {}", score.score),
+    );
+    set_text(
+        &mut texts,
+        HudField::HighScore,
+        format!("High {best}"),
+    );
+}
+
+fn update_lives(
+    lives: Res<Lives>,
+    mut texts: Query<(&mut Text, &HudField)>,
+) {
+    set_text(
+        &mut texts,
+        HudField::Lives,
+        format!("Lives {}", lives.0),
+    );
+}
+
+fn update_level(
+    level: Res<CurrentLevel>,
+    mut texts: Query<(&mut Text, &HudField)>,
+) {
+    set_text(
+        &mut texts,
+        HudField::Level,
+        format!(
+            "Level {}: {}",
+            level.0,
+            levels::level(level.0).name
+        ),
+    );
+}
+
+fn update_time(
+    timer: Res<Timer>,
+    mut texts: Query<(&mut Text, &HudField)>,
+) {
+    let seconds = timer.elapsed().as_secs();
+    set_text(
+        &mut texts,
+        HudField::Time,
+        format!("{}:{:02}", seconds / 60, seconds % 60),
+    );
+}
+
+fn update_ball_count(
+    balls: Query<(), With<Ball>>,
+    added_balls: Query<(), Added<Ball>>,
+    mut removed_balls: RemovedComponents<Ball>,
+    added: Query<(), Added<HudField>>,
+    mut texts: Query<(&mut Text, &HudField)>,
+) {
+    // drain the removals every frame so they don't
+    // pile up
+    let removed = removed_balls.iter().count() > 0;
+    if !removed
+        && added_balls.is_empty()
+        && added.is_empty()
+    {
+        return;
+    }
+    set_text(
+        &mut texts,
+        HudField::Balls,
+        format!("Balls {}", balls.iter().count()),
+    );
+}
+
+/// Each effect's name and the seconds it has left, in
+/// its own color
+fn update_powerups(
+    active: Res<ActivePowerups>,
+    fonts: Res<FontAssets>,
+    mut texts: Query<(&mut Text, &HudField)>,
+) {
+    let font = fonts.main.clone();
+    let sections: Vec<TextSection> = active
+        .iter()
+        .map(|effect| {
+            let powerup = effect.powerup;
+            let remaining = effect.timer.remaining_secs();
+            TextSection::new(
+                format!(
+                    "{} {} ",
+                    powerup.label(),
+                    remaining.ceil()
+                ),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 30.0,
+                    color: powerup
+                        .warning_color()
+                        .unwrap_or(powerup.color()),
+                },
+            )
+        })
+        .collect();
+    for (mut text, field) in texts.iter_mut() {
+        if *field != HudField::Powerups {
+            continue;
+        }
+        text.sections = if sections.is_empty() {
+            // an empty text still needs its first section
+            vec![TextSection::default()]
+        } else {
+            sections.clone()
+        };
+    }
+}
+
+fn update_multiplier(
+    combo: Res<Combo>,
+    rules: Res<ScoringRules>,
+    mut texts: Query<(
+        &mut Text,
+        &mut Visibility,
+        &HudField,
+    )>,
+) {
+    let multiplier = rules.multiplier(combo.0);
+    for (mut text, mut visibility, field) in
+        texts.iter_mut()
+    {
+        if *field != HudField::Multiplier {
+            continue;
+        }
+        text.sections[0].value = format!("x{multiplier}");
+        *visibility = if multiplier > 1 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
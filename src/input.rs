@@ -0,0 +1,522 @@
+//! Input is sampled every frame and held in
+//! [`PlayerInput`] until the next fixed tick consumes
+//! it, so presses aren't lost or doubled when the frame
+//! rate and tick rate differ.
+//!
+//! Buttons are read through [`Actions`], so players can
+//! rebind them. See [`Bindings`].
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        gamepad::{GamepadConnection, GamepadEvent},
+        InputSystem,
+    },
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, GameState, GameplaySet};
+
+pub struct PlayerInputPlugin;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerInput>()
+            .add_system(
+                clear_tick_input
+                    .in_schedule(CoreSchedule::FixedUpdate)
+                    .after(GameplaySet::React),
+            )
+            // drop presses from the menu
+            .add_system(
+                reset_input.in_schedule(OnEnter(
+                    GameState::Playing,
+                )),
+            );
+    }
+}
+
+/// Fills [`PlayerInput`] from the player's input
+/// devices. Headless games write it directly instead.
+pub struct DeviceInputPlugin;
+
+impl Plugin for DeviceInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PaddleControl>()
+            .init_resource::<GamepadDeadzone>()
+            .insert_resource(Bindings::load())
+            .add_systems(
+                (
+                    sample_actions,
+                    sample_gamepad_sticks,
+                    sample_mouse,
+                )
+                    .chain()
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputSystem),
+            )
+            .add_systems((
+                grab_cursor,
+                log_gamepad_connections,
+                save_bindings,
+            ));
+    }
+}
+
+/// Everything the player can do with a button
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Launch,
+    Fire,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Launch,
+        Action::Fire,
+        Action::Pause,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Launch => "Launch",
+            Action::Fire => "Fire",
+            Action::Pause => "Pause",
+        }
+    }
+}
+
+/// A button on any of the supported devices
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// the button on any connected gamepad
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{key:?}"),
+            Binding::Mouse(button) => {
+                format!("Mouse {button:?}")
+            }
+            Binding::Gamepad(button) => {
+                format!("Pad {button:?}")
+            }
+        }
+    }
+    fn same_device(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self)
+            == std::mem::discriminant(other)
+    }
+}
+
+/// The buttons bound to each [`Action`]. Loaded from
+/// the config directory at startup and saved back
+/// whenever it changes.
+#[derive(
+    Resource,
+    Debug,
+    Clone,
+    PartialEq,
+    Serialize,
+    Deserialize,
+)]
+pub struct Bindings(pub BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        Bindings(BTreeMap::from([
+            (
+                Action::MoveLeft,
+                vec![
+                    Key(KeyCode::A),
+                    Key(KeyCode::Left),
+                    Gamepad(GamepadButtonType::DPadLeft),
+                ],
+            ),
+            (
+                Action::MoveRight,
+                vec![
+                    Key(KeyCode::D),
+                    Key(KeyCode::Right),
+                    Gamepad(GamepadButtonType::DPadRight),
+                ],
+            ),
+            (
+                Action::Launch,
+                vec![
+                    Key(KeyCode::Space),
+                    Mouse(MouseButton::Left),
+                    Gamepad(GamepadButtonType::South),
+                ],
+            ),
+            (
+                Action::Fire,
+                vec![
+                    Key(KeyCode::W),
+                    Gamepad(GamepadButtonType::West),
+                    Gamepad(
+                        GamepadButtonType::RightTrigger2,
+                    ),
+                ],
+            ),
+            (
+                Action::Pause,
+                vec![
+                    Key(KeyCode::Escape),
+                    Gamepad(GamepadButtonType::Start),
+                ],
+            ),
+        ]))
+    }
+}
+
+impl Bindings {
+    fn path() -> Option<PathBuf> {
+        config::config_dir()
+            .map(|dir| dir.join("bindings.ron"))
+    }
+
+    /// The saved bindings, or the defaults if there
+    /// aren't any or they can't be read
+    pub fn load() -> Self {
+        let Some(path) = Bindings::path() else {
+            return Bindings::default();
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Bindings::default();
+        };
+        match ron::from_str(&contents) {
+            Ok(bindings) => bindings,
+            Err(error) => {
+                warn!(
+                    "ignoring unreadable bindings in {}: {error}",
+                    path.display()
+                );
+                Bindings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Bindings::path()
+            .ok_or("no config directory")?;
+        let contents = ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )
+        .map_err(|error| error.to_string())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|error| error.to_string())?;
+        }
+        fs::write(&path, contents)
+            .map_err(|error| error.to_string())
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bind `binding` to `action`, replacing the
+    /// action's binding on the same device. The button
+    /// is taken away from any other action it was bound
+    /// to.
+    pub fn rebind(
+        &mut self,
+        action: Action,
+        binding: Binding,
+    ) {
+        for bindings in self.0.values_mut() {
+            bindings.retain(|bound| *bound != binding);
+        }
+        let bindings = self.0.entry(action).or_default();
+        match bindings
+            .iter_mut()
+            .find(|bound| bound.same_device(&binding))
+        {
+            Some(bound) => *bound = binding,
+            None => bindings.push(binding),
+        }
+    }
+}
+
+/// Reads [`Action`]s through the current [`Bindings`]
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    bindings: Res<'w, Bindings>,
+    keys: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+}
+
+impl<'w> Actions<'w> {
+    /// Whether any button bound to `action` is held
+    pub fn pressed(&self, action: Action) -> bool {
+        self.bindings.get(action).iter().any(|binding| {
+            match *binding {
+                Binding::Key(key) => self.keys.pressed(key),
+                Binding::Mouse(button) => {
+                    self.mouse.pressed(button)
+                }
+                Binding::Gamepad(button_type) => {
+                    self.gamepads.iter().any(|gamepad| {
+                        self.gamepad_buttons.pressed(
+                            GamepadButton::new(
+                                gamepad,
+                                button_type,
+                            ),
+                        )
+                    })
+                }
+            }
+        })
+    }
+    /// Whether any button bound to `action` was
+    /// pressed this frame
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.bindings.get(action).iter().any(|binding| {
+            match *binding {
+                Binding::Key(key) => {
+                    self.keys.just_pressed(key)
+                }
+                Binding::Mouse(button) => {
+                    self.mouse.just_pressed(button)
+                }
+                Binding::Gamepad(button_type) => {
+                    self.gamepads.iter().any(|gamepad| {
+                        self.gamepad_buttons.just_pressed(
+                            GamepadButton::new(
+                                gamepad,
+                                button_type,
+                            ),
+                        )
+                    })
+                }
+            }
+        })
+    }
+}
+
+/// Which device moves the paddle
+#[derive(
+    Resource, Debug, Default, Clone, Copy, PartialEq, Eq,
+)]
+pub enum PaddleControl {
+    #[default]
+    Keyboard,
+    /// the paddle follows the cursor
+    Mouse,
+}
+
+/// Stick movement smaller than this is ignored.
+/// Anything past it is rescaled so the paddle can still
+/// move slowly.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct GamepadDeadzone(pub f32);
+
+impl Default for GamepadDeadzone {
+    fn default() -> Self {
+        GamepadDeadzone(0.2)
+    }
+}
+
+impl GamepadDeadzone {
+    pub fn apply(&self, value: f32) -> f32 {
+        if value.abs() <= self.0 {
+            0.0
+        } else {
+            (value.abs() - self.0) / (1.0 - self.0)
+                * value.signum()
+        }
+    }
+}
+
+/// What the player wants to do during the next tick
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Resource,
+    Serialize,
+    Deserialize,
+)]
+pub struct PlayerInput {
+    /// -1.0 is full speed left, 1.0 full speed right
+    pub movement: f32,
+    /// x position in the world the paddle should move
+    /// to, used instead of `movement` when set
+    pub target_x: Option<f32>,
+    /// launch a serving ball
+    pub launch: bool,
+    /// fire the paddle's guns. Nothing shoots yet,
+    /// [`crate::Powerup::Gunship`] isn't implemented.
+    pub fire: bool,
+}
+
+/// Starts each frame's sample, the gamepad sticks and
+/// mouse add to it
+fn sample_actions(
+    actions: Actions,
+    mut input: ResMut<PlayerInput>,
+) {
+    let mut movement = 0.0;
+    if actions.pressed(Action::MoveLeft) {
+        movement -= 1.0;
+    }
+    if actions.pressed(Action::MoveRight) {
+        movement += 1.0;
+    }
+    input.movement = movement;
+    // held until a tick runs, see `clear_tick_input`
+    input.launch |= actions.just_pressed(Action::Launch);
+    input.fire |= actions.just_pressed(Action::Fire);
+}
+
+/// Every connected gamepad can play, so one plugged in
+/// mid game works right away
+fn sample_gamepad_sticks(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    deadzone: Res<GamepadDeadzone>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let mut movement = input.movement;
+    for gamepad in gamepads.iter() {
+        let stick = axes
+            .get(GamepadAxis::new(
+                gamepad,
+                GamepadAxisType::LeftStickX,
+            ))
+            .unwrap_or(0.0);
+        movement += deadzone.apply(stick);
+    }
+    input.movement = movement.clamp(-1.0, 1.0);
+}
+
+fn sample_mouse(
+    control: Res<PaddleControl>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut input: ResMut<PlayerInput>,
+) {
+    input.target_x = None;
+    if *control != PaddleControl::Mouse {
+        return;
+    }
+    // the camera is zoomed out, so window and world
+    // coordinates differ
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    if let (Some(cursor), Ok((camera, transform))) =
+        (cursor, cameras.get_single())
+    {
+        input.target_x = camera
+            .viewport_to_world(transform, cursor)
+            .map(|ray| ray.origin.x);
+    }
+}
+
+/// Keep the cursor out of the way while the mouse is
+/// steering the paddle
+fn grab_cursor(
+    control: Res<PaddleControl>,
+    state: Res<State<GameState>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !control.is_changed() && !state.is_changed() {
+        return;
+    }
+    let grab = *control == PaddleControl::Mouse
+        && state.0 == GameState::Playing;
+    for mut window in windows.iter_mut() {
+        window.cursor.visible = !grab;
+        window.cursor.grab_mode = if grab {
+            CursorGrabMode::Confined
+        } else {
+            CursorGrabMode::None
+        };
+    }
+}
+
+/// One-shot actions only apply to the tick that
+/// follows them
+fn clear_tick_input(mut input: ResMut<PlayerInput>) {
+    input.launch = false;
+    input.fire = false;
+}
+
+fn log_gamepad_connections(
+    mut events: EventReader<GamepadEvent>,
+) {
+    for event in events.iter() {
+        if let GamepadEvent::Connection(connection) = event
+        {
+            match &connection.connection {
+                GamepadConnection::Connected(info) => {
+                    info!(
+                        "gamepad {} connected: {}",
+                        connection.gamepad.id, info.name
+                    )
+                }
+                GamepadConnection::Disconnected => info!(
+                    "gamepad {} disconnected",
+                    connection.gamepad.id
+                ),
+            }
+        }
+    }
+}
+
+fn reset_input(mut input: ResMut<PlayerInput>) {
+    *input = PlayerInput::default();
+}
+
+/// Bindings are only changed from the menu, so saving
+/// straight away is cheap
+fn save_bindings(bindings: Res<Bindings>) {
+    if !bindings.is_changed() || bindings.is_added() {
+        return;
+    }
+    if let Err(error) = bindings.save() {
+        warn!("couldn't save bindings: {error}");
+    }
+}
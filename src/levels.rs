@@ -1,17 +1,35 @@
 use crate::blocks::*;
-use bevy::prelude::*;
 
 use Block::*;
 
-pub const LEVEL_1: [[Option<Block>; 11]; 10] = [
-    [None; 11],
-    [None; 11],
-    [None; 11],
-    [None; 11],
-    [Some(Silver); 11],
-    [Some(Red); 11],
-    [Some(Blue); 11],
-    [Some(Orange); 11],
-    [Some(Pink); 11],
-    [Some(Green); 11],
-];
+/// A named layout of blocks
+pub struct Level {
+    /// shown on the HUD next to the level's number
+    pub name: &'static str,
+    /// rows of blocks, top first
+    pub blocks: [[Option<Block>; 11]; 10],
+}
+
+pub const LEVEL_1: Level = Level {
+    name: "Rainbow",
+    blocks: [
+        [None; 11],
+        [None; 11],
+        [None; 11],
+        [None; 11],
+        [Some(Silver); 11],
+        [Some(Red); 11],
+        [Some(Blue); 11],
+        [Some(Orange); 11],
+        [Some(Pink); 11],
+        [Some(Green); 11],
+    ],
+};
+
+pub const LEVELS: [Level; 1] = [LEVEL_1];
+
+/// The layout for level `number`, counting from 1.
+/// Levels past the last one repeat it.
+pub fn level(number: usize) -> &'static Level {
+    &LEVELS[number.clamp(1, LEVELS.len()) - 1]
+}
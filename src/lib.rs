@@ -1,25 +1,202 @@
-pub mod board;
-use board::*;
-pub mod custom_commands;
-use custom_commands::*;
 pub mod assets;
+pub mod ball;
+pub mod barrier;
 pub mod blocks;
+pub mod board;
+pub mod config;
+pub mod custom_commands;
+pub mod gameplay;
+pub mod high_scores;
+pub mod hud;
+pub mod input;
 pub mod levels;
+pub mod paddle;
+pub mod physics;
+pub mod powerups;
+pub mod presentation;
+pub mod replay;
 pub mod scoring;
+pub mod serve;
 pub mod ui;
 
+pub use powerups::Powerup;
+
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::Group;
+use rand::{rngs::StdRng, SeedableRng};
 
-pub const STARTING_GAME_STATE: GameState = GameState::Menu;
+pub const STARTING_GAME_STATE: GameState = GameState::Loading;
 
 #[derive(
     Default, Debug, Clone, Copy, PartialEq, Eq, Hash, States,
 )]
 pub enum GameState {
+    /// waiting for [`assets`] to load
     #[default]
+    Loading,
+    /// some assets couldn't be loaded, the game can't
+    /// start
+    LoadingFailed,
     Menu,
     Playing,
     Paused,
+    /// the last life was lost
+    GameOver,
+    /// every block that can be broken was
+    Victory,
+}
+
+impl GameState {
+    /// Whether `self` is `state` or nested in it. A
+    /// paused game is still being played.
+    pub fn is_within(&self, state: GameState) -> bool {
+        *self == state
+            || *self == GameState::Paused
+                && state == GameState::Playing
+    }
+}
+
+/// Ties an entity to a state, it's despawned once the
+/// game is no longer within that state. See
+/// [`GameState::is_within`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateScoped(pub GameState);
+
+/// Gameplay runs on [`CoreSchedule::FixedUpdate`],
+/// right after the physics step it reacts to. See
+/// [`physics`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub enum GameplaySet {
+    /// read input and this tick's collision events,
+    /// advance timers
+    Tick,
+    /// handle the events sent during
+    /// [`GameplaySet::Tick`]
+    React,
+}
+
+/// Solver group of every block except
+/// [`blocks::Block::Gold`]. Fireballs exclude this group
+/// so they pass through instead of bouncing.
+pub const DESTRUCTIBLE_BLOCK_GROUP: Group = Group::GROUP_2;
+
+/// Collision group every ball is a member of
+pub const BALL_GROUP: Group = Group::GROUP_3;
+
+/// Collision group of [`barrier::Barrier`]. The barrier
+/// only collides with balls, and the paddle's character
+/// controller ignores it.
+pub const BARRIER_GROUP: Group = Group::GROUP_4;
+
+/// Picked with the difficulty button on the main menu.
+/// Replays and the high score table record it.
+#[derive(
+    Resource,
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Whether harmful powerups can drop
+    pub fn curses(&self) -> bool {
+        !matches!(self, Difficulty::Easy)
+    }
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+    /// The difficulty after this one on the menu
+    /// button, wrapping around
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+}
+
+/// Set while a game is underway, paused or not.
+/// Entering [`GameState::Playing`] while it's set
+/// resumes that game instead of starting a new one.
+#[derive(
+    Resource, Default, Debug, Clone, Copy, PartialEq, Eq,
+)]
+pub struct GameInProgress(pub bool);
+
+/// Run condition for systems that set up a new game
+/// on entering [`GameState::Playing`]
+pub fn starting_new_game(
+    in_progress: Res<GameInProgress>,
+) -> bool {
+    !in_progress.0
+}
+
+/// Run condition for systems that wrap up a game
+pub fn game_in_progress(
+    in_progress: Res<GameInProgress>,
+) -> bool {
+    in_progress.0
+}
+
+/// Seed of the [`GameRng`] for the current game
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameSeed(pub u64);
+
+/// Where each game's [`GameSeed`] comes from. A replay
+/// being watched always brings its own.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedSource {
+    /// every game is played with the same seed, for
+    /// tests
+    Fixed(u64),
+    /// a fresh seed for every game
+    Random,
+}
+
+/// The only source of randomness gameplay may use, so
+/// a game can be played back exactly. Reseeded from
+/// [`GameSeed`] at the start of every game.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn from_seed(seed: GameSeed) -> Self {
+        GameRng(StdRng::seed_from_u64(seed.0))
+    }
+}
+
+#[derive(
+    Resource,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Deref,
+    DerefMut,
+)]
+pub struct Lives(pub u32);
+
+impl Default for Lives {
+    fn default() -> Self {
+        Lives(3)
+    }
 }
 
 #[derive(Component)]
@@ -34,6 +211,8 @@ pub struct PlayingAreaBorder;
 #[derive(Component)]
 pub struct DespawnArea;
 
+/// Keeps a ball sitting on the paddle, `diff` along
+/// the x axis from the paddle's center
 #[derive(Component)]
 pub struct ConnectToPaddle {
     pub diff: f32,
@@ -42,16 +221,8 @@ pub struct ConnectToPaddle {
 #[derive(Component, Deref, DerefMut)]
 pub struct Damage(pub u8);
 
-#[derive(Component, Debug)]
-pub enum Powerup {
-    TripleBall,
-    WidePaddle,
-    Gunship,
-    Sticky,
-    Life,
-}
-
-pub struct SpawnThreeBallsEvent;
+/// Split every ball in play, see [`ball::Multiball`]
+pub struct MultiballEvent;
 
 #[derive(Component)]
 pub struct BallContactEffect;
@@ -0,0 +1,210 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    ball::Magnet,
+    board::{self, Board},
+    powerups::ActivePowerups,
+    GameplaySet, Paddle, Powerup,
+};
+
+pub const PADDLE_WIDTH: f32 = 200.0;
+pub const PADDLE_HEIGHT: f32 = 20.0;
+pub const PADDLE_COLOR: Color = Color::BLACK;
+
+/// Height of the paddle's center, which never changes
+pub fn paddle_y(board: &Board) -> f32 {
+    board.physical.y / 2.0
+        + board.u8_cell_to_physical(3, board::Axis::Y)
+}
+
+pub struct PaddlePlugin;
+
+impl Plugin for PaddlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Steering>()
+            .add_system(
+                track_paddle_velocity
+                    .in_set(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                apply_paddle_effects
+                    .in_set(GameplaySet::React)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+/// How the paddle steers the balls it hits
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct Steering {
+    /// horizontal speed a ball gets for every pixel it
+    /// lands away from the paddle's center, before
+    /// [`crate::ball::BallSpeed`] is applied
+    pub offset: f32,
+    /// share of the paddle's horizontal velocity
+    /// passed on to the ball
+    pub velocity: f32,
+    /// curve, in radians per second, a ball picks up
+    /// for every pixel per second the paddle was
+    /// moving. Zero turns spin off.
+    pub spin: f32,
+    /// share of its spin a ball loses every second
+    pub spin_decay: f32,
+}
+
+impl Default for Steering {
+    fn default() -> Self {
+        Steering {
+            offset: 7.5,
+            velocity: 0.5,
+            spin: 0.0,
+            spin_decay: 1.5,
+        }
+    }
+}
+
+/// How fast the paddle actually moved during the last
+/// tick, in pixels per second
+#[derive(Component, Debug, Default)]
+pub struct PaddleVelocity {
+    pub x: f32,
+    last_x: Option<f32>,
+}
+
+/// Measured rather than taken from the input, so walls
+/// and freezes are accounted for
+pub fn track_paddle_velocity(
+    fixed_time: Res<FixedTime>,
+    mut paddles: Query<(&Transform, &mut PaddleVelocity)>,
+) {
+    let dt = fixed_time.period.as_secs_f32();
+    for (transform, mut velocity) in paddles.iter_mut() {
+        let x = transform.translation.x;
+        velocity.x = velocity
+            .last_x
+            .map_or(0.0, |last_x| (x - last_x) / dt);
+        velocity.last_x = Some(x);
+    }
+}
+
+/// Width of the paddle given the effects currently in
+/// play
+pub fn paddle_width(active: &ActivePowerups) -> f32 {
+    if active.is_active(Powerup::ShrinkPaddle) {
+        PADDLE_WIDTH * 0.6
+    } else {
+        PADDLE_WIDTH
+    }
+}
+
+fn apply_paddle_effects(
+    active: Res<ActivePowerups>,
+    mut paddles: Query<
+        (&mut Sprite, &mut Collider),
+        With<Paddle>,
+    >,
+) {
+    if !active.is_changed() {
+        return;
+    }
+    let width = paddle_width(&active);
+    // tint the paddle so it's clear why it isn't
+    // responding normally
+    let color = if active.is_active(Powerup::FreezePaddle) {
+        Color::hex("8fd3ff").unwrap()
+    } else if active.is_active(Powerup::ReverseControls) {
+        Color::hex("8a2be2").unwrap()
+    } else {
+        PADDLE_COLOR
+    };
+    for (mut sprite, mut collider) in paddles.iter_mut() {
+        sprite.color = color;
+        if sprite.custom_size.map(|size| size.x)
+            != Some(width)
+        {
+            sprite.custom_size =
+                Some(Vec2::new(width, PADDLE_HEIGHT));
+            *collider = Collider::cuboid(
+                width / 2.0,
+                PADDLE_HEIGHT / 2.0,
+            );
+        }
+    }
+}
+
+/// The dome drawn over the paddle while the magnet is
+/// active
+#[derive(Component)]
+pub(crate) struct MagnetField;
+
+pub(crate) fn show_magnet_field(
+    mut commands: Commands,
+    active: Res<ActivePowerups>,
+    magnet: Res<Magnet>,
+    paddles: Query<Entity, With<Paddle>>,
+    fields: Query<Entity, With<MagnetField>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+    let is_active = active.is_active(Powerup::Magnet);
+    if is_active && fields.is_empty() {
+        for paddle in paddles.iter() {
+            commands.entity(paddle).with_children(
+                |parent| {
+                    parent.spawn((
+                        ShapeBundle {
+                            path: magnet_field_path(
+                                magnet.range,
+                            ),
+                            // behind the paddle
+                            transform: Transform::from_xyz(
+                                0.0, 0.0, -1.0,
+                            ),
+                            ..default()
+                        },
+                        Fill::color(Color::rgba(
+                            0.55, 0.75, 1.0, 0.08,
+                        )),
+                        Stroke::new(
+                            Color::rgba(
+                                0.55, 0.75, 1.0, 0.4,
+                            ),
+                            3.0,
+                        ),
+                        MagnetField,
+                    ));
+                },
+            );
+        }
+    } else if !is_active {
+        for field in fields.iter() {
+            commands.entity(field).despawn_recursive();
+        }
+    }
+}
+
+/// A half circle sitting on top of the paddle
+fn magnet_field_path(range: f32) -> Path {
+    let mut builder = PathBuilder::new();
+    builder.move_to(Vec2::new(range, 0.0));
+    builder.arc(Vec2::ZERO, Vec2::splat(range), PI, 0.0);
+    builder.close();
+    builder.build()
+}
+
+pub(crate) fn pulse_magnet_field(
+    time: Res<Time>,
+    mut fields: Query<&mut Transform, With<MagnetField>>,
+) {
+    let scale =
+        0.97 + (time.elapsed_seconds() * 4.0).sin() * 0.03;
+    for mut transform in fields.iter_mut() {
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+}
@@ -0,0 +1,185 @@
+//! Physics and gameplay advance in fixed ticks on
+//! [`CoreSchedule::FixedUpdate`] so the game plays the
+//! same at any frame rate. Rendering interpolates
+//! between the last two ticks, see
+//! [`RenderInterpolationPlugin`].
+use bevy::{
+    ecs::schedule::ExecutorKind, prelude::*,
+    transform::TransformSystem,
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{GameState, GameplaySet};
+
+/// Fixed ticks per second
+pub const TICK_RATE: f32 = 60.0;
+pub const PIXELS_PER_METER: f32 = 100.0;
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        let dt = 1.0 / TICK_RATE;
+        app.insert_resource(FixedTime::new_from_secs(dt))
+            .add_plugin(
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
+                    PIXELS_PER_METER,
+                )
+                .with_default_system_setup(false),
+            )
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::Fixed {
+                    dt,
+                    substeps: 1,
+                },
+                ..default()
+            });
+
+        app.edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+            // systems that aren't explicitly ordered always
+            // run in the same order, which keeps the
+            // simulation deterministic
+            schedule
+                .set_executor_kind(ExecutorKind::SingleThreaded);
+            schedule.configure_sets(
+                (
+                    PhysicsSet::SyncBackend,
+                    PhysicsSet::SyncBackendFlush,
+                    PhysicsSet::StepSimulation,
+                    PhysicsSet::Writeback,
+                    GameplaySet::Tick,
+                    GameplaySet::React,
+                )
+                    .chain(),
+            );
+            // everything freezes outside of play
+            schedule
+                .configure_set(
+                    PhysicsSet::SyncBackend
+                        .run_if(in_state(GameState::Playing)),
+                )
+                .configure_set(
+                    PhysicsSet::SyncBackendFlush
+                        .run_if(in_state(GameState::Playing)),
+                )
+                .configure_set(
+                    PhysicsSet::StepSimulation
+                        .run_if(in_state(GameState::Playing)),
+                )
+                .configure_set(
+                    PhysicsSet::Writeback
+                        .run_if(in_state(GameState::Playing)),
+                )
+                .configure_set(
+                    GameplaySet::Tick
+                        .run_if(in_state(GameState::Playing)),
+                )
+                .configure_set(
+                    GameplaySet::React
+                        .run_if(in_state(GameState::Playing)),
+                );
+
+            for set in [
+                PhysicsSet::SyncBackend,
+                PhysicsSet::SyncBackendFlush,
+                PhysicsSet::StepSimulation,
+                PhysicsSet::Writeback,
+            ] {
+                schedule.add_systems(
+                    RapierPhysicsPlugin::<NoUserData>::get_systems(
+                        set.clone(),
+                    )
+                    .in_base_set(set),
+                );
+            }
+        });
+    }
+}
+
+/// Smooths out movement on screen by drawing
+/// [`InterpolatedTranslation`] entities between their
+/// last two simulated positions
+pub struct RenderInterpolationPlugin;
+
+impl Plugin for RenderInterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            restore_translation
+                .in_base_set(CoreSet::PreUpdate),
+        )
+        .add_system(
+            record_translation
+                .in_schedule(CoreSchedule::FixedUpdate)
+                .after(GameplaySet::React)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_system(
+            interpolate_translation
+                .in_base_set(CoreSet::PostUpdate)
+                .before(TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Entities that move during the simulation and
+/// should be drawn smoothly between ticks
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InterpolatedTranslation {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl InterpolatedTranslation {
+    pub fn new(translation: Vec3) -> Self {
+        InterpolatedTranslation {
+            previous: translation,
+            current: translation,
+        }
+    }
+}
+
+/// Put back the simulated translation before the next
+/// tick, so the physics backend doesn't mistake the
+/// interpolated one for a teleport.
+fn restore_translation(
+    mut query: Query<(
+        &InterpolatedTranslation,
+        &mut Transform,
+    )>,
+) {
+    for (interpolated, mut transform) in query.iter_mut() {
+        if transform.translation != interpolated.current {
+            transform.translation = interpolated.current;
+        }
+    }
+}
+
+fn record_translation(
+    mut query: Query<(
+        &Transform,
+        &mut InterpolatedTranslation,
+    )>,
+) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = transform.translation;
+    }
+}
+
+fn interpolate_translation(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(
+        &InterpolatedTranslation,
+        &mut Transform,
+    )>,
+) {
+    let alpha = (fixed_time.accumulated().as_secs_f32()
+        / fixed_time.period.as_secs_f32())
+    .min(1.0);
+    for (interpolated, mut transform) in query.iter_mut() {
+        transform.translation = interpolated
+            .previous
+            .lerp(interpolated.current, alpha);
+    }
+}
@@ -0,0 +1,459 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use rand::Rng;
+
+use crate::{assets::FontAssets, Difficulty, GameplaySet};
+
+pub struct PowerupPlugin;
+
+impl Plugin for PowerupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActivePowerups>()
+            .add_event::<PowerupExpired>()
+            .add_event::<PowerupCollected>()
+            .add_system(
+                tick_active_powerups
+                    .in_set(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+#[derive(
+    Component, Debug, Clone, Copy, PartialEq, Eq, Hash,
+)]
+pub enum Powerup {
+    TripleBall,
+    WidePaddle,
+    Gunship,
+    Sticky,
+    Life,
+    SlowBall,
+    FastBall,
+    Fireball,
+    ShrinkPaddle,
+    ReverseControls,
+    FreezePaddle,
+    Magnet,
+    Barrier,
+}
+
+/// The looping animation a falling powerup plays so
+/// players can tell what it is before catching it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdleAnimation {
+    /// grow and shrink uniformly
+    Pulse { speed: f32, amount: f32 },
+    /// flip around the y axis like a coin
+    Spin { speed: f32 },
+    /// jitter side to side, used to warn about curses
+    Shake { speed: f32, amount: f32 },
+}
+
+impl Powerup {
+    /// Powerups that can drop from destroyed blocks
+    pub const DROPPABLE: [Powerup; 9] = [
+        Powerup::TripleBall,
+        Powerup::SlowBall,
+        Powerup::FastBall,
+        Powerup::Fireball,
+        Powerup::Magnet,
+        Powerup::Barrier,
+        Powerup::ShrinkPaddle,
+        Powerup::ReverseControls,
+        Powerup::FreezePaddle,
+    ];
+
+    /// Pick a drop, leaving out curses if the
+    /// difficulty doesn't allow them
+    pub fn random(
+        rng: &mut impl Rng,
+        difficulty: Difficulty,
+    ) -> Powerup {
+        let droppable: Vec<Powerup> = Powerup::DROPPABLE
+            .into_iter()
+            .filter(|powerup| {
+                difficulty.curses() || !powerup.is_curse()
+            })
+            .collect();
+        droppable[rng.gen_range(0..droppable.len())]
+    }
+    /// Curses hurt the player when caught
+    pub fn is_curse(&self) -> bool {
+        matches!(
+            self,
+            Powerup::FastBall
+                | Powerup::ShrinkPaddle
+                | Powerup::ReverseControls
+                | Powerup::FreezePaddle
+        )
+    }
+    /// How long the effect lasts once caught. `None`
+    /// for powerups that apply instantly.
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            Powerup::SlowBall | Powerup::FastBall => {
+                Some(Duration::from_secs(10))
+            }
+            Powerup::Fireball => {
+                Some(Duration::from_secs(8))
+            }
+            Powerup::ShrinkPaddle => {
+                Some(Duration::from_secs(10))
+            }
+            Powerup::ReverseControls => {
+                Some(Duration::from_secs(6))
+            }
+            Powerup::FreezePaddle => {
+                Some(Duration::from_secs(1))
+            }
+            Powerup::Magnet => {
+                Some(Duration::from_secs(12))
+            }
+            Powerup::Barrier => {
+                Some(Duration::from_secs(20))
+            }
+            _ => None,
+        }
+    }
+    /// Factor applied to [`crate::ball::BallSpeed`]
+    /// while this powerup is active
+    pub fn speed_multiplier(&self) -> Option<f32> {
+        match self {
+            Powerup::SlowBall => Some(0.6),
+            Powerup::FastBall => Some(1.5),
+            _ => None,
+        }
+    }
+    /// Curses share a dark body so they read as a
+    /// warning, see [`Powerup::warning_color`]
+    pub fn color(&self) -> Color {
+        match self {
+            Powerup::FastBall
+            | Powerup::ShrinkPaddle
+            | Powerup::ReverseControls
+            | Powerup::FreezePaddle => {
+                Color::hex("2a1e24").unwrap()
+            }
+            Powerup::TripleBall => {
+                Color::hex("46b1e6").unwrap()
+            }
+            Powerup::WidePaddle => {
+                Color::hex("5ccf7a").unwrap()
+            }
+            Powerup::Gunship => {
+                Color::hex("9a6ee8").unwrap()
+            }
+            Powerup::Sticky => {
+                Color::hex("e8c63c").unwrap()
+            }
+            Powerup::Life => Color::hex("e8566e").unwrap(),
+            Powerup::SlowBall => {
+                Color::hex("3cc8c8").unwrap()
+            }
+            Powerup::Fireball => {
+                Color::hex("d2321e").unwrap()
+            }
+            Powerup::Magnet => {
+                Color::hex("c0c4d6").unwrap()
+            }
+            Powerup::Barrier => {
+                Color::hex("2fb5a0").unwrap()
+            }
+        }
+    }
+    /// Outline and glyph color for curses
+    pub fn warning_color(&self) -> Option<Color> {
+        self.is_curse()
+            .then(|| Color::hex("ff2d2d").unwrap())
+    }
+    /// The name shown on the HUD while it's active
+    pub fn label(&self) -> &'static str {
+        match self {
+            Powerup::TripleBall => "Triple Ball",
+            Powerup::WidePaddle => "Wide Paddle",
+            Powerup::Gunship => "Gunship",
+            Powerup::Sticky => "Sticky",
+            Powerup::Life => "Extra Life",
+            Powerup::SlowBall => "Slow Ball",
+            Powerup::FastBall => "Fast Ball",
+            Powerup::Fireball => "Fireball",
+            Powerup::ShrinkPaddle => "Shrink",
+            Powerup::ReverseControls => "Reversed",
+            Powerup::FreezePaddle => "Frozen",
+            Powerup::Magnet => "Magnet",
+            Powerup::Barrier => "Barrier",
+        }
+    }
+    /// The letter printed on the powerup capsule
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Powerup::TripleBall => "3",
+            Powerup::WidePaddle => "W",
+            Powerup::Gunship => "G",
+            Powerup::Sticky => "S",
+            Powerup::Life => "+",
+            Powerup::SlowBall => "<",
+            Powerup::FastBall => ">",
+            Powerup::Fireball => "F",
+            Powerup::ShrinkPaddle => "-",
+            Powerup::ReverseControls => "R",
+            Powerup::FreezePaddle => "X",
+            Powerup::Magnet => "M",
+            Powerup::Barrier => "_",
+        }
+    }
+    pub fn idle_animation(&self) -> IdleAnimation {
+        match self {
+            Powerup::TripleBall => {
+                IdleAnimation::Spin { speed: 3.0 }
+            }
+            Powerup::WidePaddle => IdleAnimation::Pulse {
+                speed: 4.0,
+                amount: 0.15,
+            },
+            Powerup::Gunship => {
+                IdleAnimation::Spin { speed: 5.0 }
+            }
+            Powerup::Sticky => IdleAnimation::Pulse {
+                speed: 2.0,
+                amount: 0.1,
+            },
+            Powerup::Life => IdleAnimation::Pulse {
+                speed: 6.0,
+                amount: 0.2,
+            },
+            Powerup::SlowBall => IdleAnimation::Pulse {
+                speed: 1.0,
+                amount: 0.1,
+            },
+            Powerup::Fireball => IdleAnimation::Pulse {
+                speed: 10.0,
+                amount: 0.15,
+            },
+            Powerup::Magnet => IdleAnimation::Pulse {
+                speed: 3.0,
+                amount: 0.2,
+            },
+            Powerup::Barrier => {
+                IdleAnimation::Spin { speed: 2.0 }
+            }
+            Powerup::FastBall
+            | Powerup::ShrinkPaddle
+            | Powerup::ReverseControls
+            | Powerup::FreezePaddle => {
+                IdleAnimation::Shake {
+                    speed: 40.0,
+                    amount: 3.0,
+                }
+            }
+        }
+    }
+}
+
+/// Marks the visual child of a powerup. The animation
+/// is applied to this child rather than the powerup
+/// itself so the sensor collider keeps its shape.
+#[derive(Component, Debug)]
+pub struct PowerupIdle(pub IdleAnimation);
+
+/// Give newly spawned powerups their capsule and glyph
+pub(crate) fn add_powerup_visuals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    fonts: Res<FontAssets>,
+    powerups: Query<(Entity, &Powerup), Added<Powerup>>,
+) {
+    for (entity, powerup) in powerups.iter() {
+        let capsule = meshes
+            .add(
+                shape::Capsule {
+                    radius: 10.0,
+                    depth: 40.0,
+                    ..Default::default()
+                }
+                .into(),
+            )
+            .into();
+        let color_material = materials
+            .add(ColorMaterial::from(powerup.color()));
+        // curses get a bright outline behind the capsule
+        let warning_outline =
+            powerup.warning_color().map(|color| {
+                let mesh = meshes
+                    .add(
+                        shape::Capsule {
+                            radius: 13.0,
+                            depth: 40.0,
+                            ..Default::default()
+                        }
+                        .into(),
+                    )
+                    .into();
+                let material = materials
+                    .add(ColorMaterial::from(color));
+                (mesh, material)
+            });
+        let font = fonts.main.clone();
+
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn((
+                    MaterialMesh2dBundle {
+                        mesh: capsule,
+                        material: color_material,
+                        ..default()
+                    },
+                    PowerupIdle(powerup.idle_animation()),
+                ))
+                .with_children(|parent| {
+                    if let Some((mesh, material)) =
+                        warning_outline
+                    {
+                        parent.spawn(
+                            MaterialMesh2dBundle {
+                                mesh,
+                                material,
+                                transform:
+                                    Transform::from_xyz(
+                                        0.0, 0.0, -0.5,
+                                    ),
+                                ..default()
+                            },
+                        );
+                    }
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            powerup.glyph(),
+                            TextStyle {
+                                font,
+                                font_size: 18.0,
+                                color: powerup
+                                    .warning_color()
+                                    .unwrap_or(
+                                        Color::WHITE,
+                                    ),
+                            },
+                        )
+                        .with_alignment(
+                            TextAlignment::Center,
+                        ),
+                        transform: Transform::from_xyz(
+                            0.0, 0.0, 1.0,
+                        ),
+                        ..default()
+                    });
+                });
+        });
+    }
+}
+
+pub(crate) fn animate_powerups(
+    time: Res<Time>,
+    mut visuals: Query<(&PowerupIdle, &mut Transform)>,
+) {
+    let t = time.elapsed_seconds();
+    for (PowerupIdle(animation), mut transform) in
+        visuals.iter_mut()
+    {
+        match *animation {
+            IdleAnimation::Pulse { speed, amount } => {
+                transform.scale = Vec3::splat(
+                    1.0 + (t * speed).sin() * amount,
+                );
+            }
+            IdleAnimation::Spin { speed } => {
+                transform.scale.x = (t * speed).cos();
+            }
+            IdleAnimation::Shake { speed, amount } => {
+                transform.translation.x =
+                    (t * speed).sin() * amount;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ActivePowerup {
+    pub powerup: Powerup,
+    pub timer: Timer,
+}
+
+/// Every timed powerup effect currently in play.
+/// Systems that implement an effect should check
+/// [`ActivePowerups::is_active`] rather than keeping
+/// their own timers.
+#[derive(Debug, Default, Resource)]
+pub struct ActivePowerups(Vec<ActivePowerup>);
+
+/// Sent when the paddle catches a powerup
+pub struct PowerupCollected(pub Powerup);
+
+/// Sent when a timed powerup runs out
+pub struct PowerupExpired(pub Powerup);
+
+impl ActivePowerups {
+    /// Start the effect, or restart its timer if it
+    /// is already running
+    pub fn activate(
+        &mut self,
+        powerup: Powerup,
+        duration: Duration,
+    ) {
+        match self
+            .0
+            .iter_mut()
+            .find(|active| active.powerup == powerup)
+        {
+            Some(active) => {
+                active.timer =
+                    Timer::new(duration, TimerMode::Once);
+            }
+            None => self.0.push(ActivePowerup {
+                powerup,
+                timer: Timer::new(
+                    duration,
+                    TimerMode::Once,
+                ),
+            }),
+        }
+    }
+    pub fn deactivate(&mut self, powerup: Powerup) {
+        self.0.retain(|active| active.powerup != powerup);
+    }
+    pub fn is_active(&self, powerup: Powerup) -> bool {
+        self.0
+            .iter()
+            .any(|active| active.powerup == powerup)
+    }
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = &ActivePowerup> {
+        self.0.iter()
+    }
+}
+
+/// Advance every effect's timer, before anything can
+/// start or end an effect this tick
+pub fn tick_active_powerups(
+    fixed_time: Res<FixedTime>,
+    mut active: ResMut<ActivePowerups>,
+    mut expired: EventWriter<PowerupExpired>,
+) {
+    if active.0.is_empty() {
+        return;
+    }
+    for effect in active.0.iter_mut() {
+        effect.timer.tick(fixed_time.period);
+    }
+    active.0.retain(|effect| {
+        if effect.timer.finished() {
+            expired.send(PowerupExpired(effect.powerup));
+            false
+        } else {
+            true
+        }
+    });
+}
@@ -0,0 +1,267 @@
+//! Everything that only affects how the game looks.
+//! Gameplay entities are spawned without visuals, the
+//! systems here dress them up as they appear.
+use bevy::{prelude::*, sprite::Anchor};
+use bevy_hanabi::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    assets::{
+        fonts_loaded, images_loaded, AssetsPlugin,
+        ImageAssets,
+    },
+    ball::{
+        add_ball_shapes, add_fireball_visuals,
+        remove_fireball_visuals, setup_fireball_trail,
+        FireballTrail,
+    },
+    board::Board,
+    paddle::{pulse_magnet_field, show_magnet_field},
+    physics::RenderInterpolationPlugin,
+    powerups::{add_powerup_visuals, animate_powerups},
+    serve::show_aim_indicator,
+    starting_new_game, Ball, GameState, StateScoped,
+};
+
+pub struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(AssetsPlugin)
+            .add_plugin(ShapePlugin)
+            .add_plugin(HanabiPlugin)
+            .add_plugin(RenderInterpolationPlugin)
+            .add_startup_system(spawn_camera)
+            // these need the loaded images, which don't
+            // exist if loading failed
+            .add_systems(
+                (spawn_background, setup_fireball_trail)
+                    .distributive_run_if(images_loaded)
+                    .in_schedule(OnExit(
+                        GameState::Loading,
+                    )),
+            )
+            .add_systems(
+                (
+                    spawn_board_decorations,
+                    spawn_impact_effect,
+                )
+                    .distributive_run_if(starting_new_game)
+                    .in_schedule(OnEnter(
+                        GameState::Playing,
+                    )),
+            )
+            .add_systems(
+                (
+                    add_ball_shapes,
+                    // fireball visuals recolor the shape
+                    apply_system_buffers,
+                    add_fireball_visuals.run_if(
+                        resource_exists::<FireballTrail>(),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems((
+                remove_fireball_visuals,
+                add_powerup_visuals.run_if(fonts_loaded),
+            ))
+            .add_systems(
+                (animate_powerups, pulse_magnet_field)
+                    .distributive_run_if(not_paused),
+            )
+            .add_systems(
+                (
+                    show_ball_impacts,
+                    show_magnet_field,
+                    show_aim_indicator,
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+/// Idle animations hold still while the game is paused
+fn not_paused(state: Res<State<GameState>>) -> bool {
+    state.0 != GameState::Paused
+}
+
+fn spawn_camera(mut commands: Commands, board: Res<Board>) {
+    commands.spawn(Camera2dBundle {
+        projection: OrthographicProjection {
+            scale: 2.0,
+            ..default()
+        },
+        transform: Transform::from_xyz(
+            board.physical.x / 2.0,
+            board.physical.y / 2.0,
+            1000.0,
+        ),
+        ..default()
+    });
+}
+
+fn spawn_background(
+    mut commands: Commands,
+    images: Res<ImageAssets>,
+    board: Res<Board>,
+) {
+    commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            // color: todo!(),
+            custom_size: Some(Vec2::new(
+                1920.0 * 2.0,
+                1080.0 * 2.0,
+            )),
+            anchor: Anchor::Center,
+            ..Default::default()
+        },
+        transform: Transform::from_xyz(
+            board.physical.x / 2.0,
+            board.physical.y / 2.0,
+            0.0,
+        ),
+        texture: images.background.clone(),
+        ..Default::default()
+    });
+}
+
+fn spawn_board_decorations(
+    mut commands: Commands,
+    board: Res<Board>,
+) {
+    // Playing Area Exterior
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::Rgba {
+                    red: 1.0,
+                    green: 1.0,
+                    blue: 1.0,
+                    alpha: 0.3,
+                },
+                custom_size: Some(Vec2::new(
+                    board.physical.x,
+                    board.physical.y,
+                )),
+                anchor: Anchor::BottomLeft,
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 1.0),
+            ..Default::default()
+        },
+        StateScoped(GameState::Playing),
+    ));
+
+    // border
+    let shape = shapes::Rectangle {
+        extents: Vec2::new(
+            board.physical.x + 10.0,
+            board.physical.y + 10.0,
+        ),
+        ..Default::default()
+    };
+
+    commands.spawn((
+        ShapeBundle {
+            path: GeometryBuilder::build_as(&shape),
+            transform: Transform::from_xyz(
+                board.physical.x / 2.0,
+                board.physical.y / 2.0,
+                2.0,
+            ),
+            ..default()
+        },
+        Fill::color(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+        Stroke::new(
+            Color::rgba(82.0, 90.0, 94.0, 1.0),
+            10.0,
+        ),
+        StateScoped(GameState::Playing),
+    ));
+}
+
+const BALL_RADIUS: f32 = 20.05;
+
+/// Particles that burst from a ball when it hits
+/// something
+#[derive(Component)]
+struct ImpactEffect;
+
+fn spawn_impact_effect(
+    mut commands: Commands,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(1.0, 0.0, 0.0, 1.0));
+    gradient.add_key(1.0, Vec4::new(1.0, 0.0, 1.0, 0.0));
+
+    let spawner = Spawner::once(30.0.into(), false);
+    let effect = effects.add(
+        EffectAsset {
+            name: "Impact".into(),
+            capacity: 32768,
+            spawner,
+            ..Default::default()
+        }
+        .init(InitPositionSphereModifier {
+            center: Vec3::ZERO,
+            radius: BALL_RADIUS,
+            dimension: ShapeDimension::Surface,
+        })
+        .init(InitVelocitySphereModifier {
+            center: Vec3::ZERO,
+            speed: 10.2.into(),
+        })
+        .init(InitLifetimeModifier {
+            lifetime: 5_f32.into(),
+        })
+        .render(SizeOverLifetimeModifier {
+            gradient: Gradient::constant(Vec2::splat(
+                10.05,
+            )),
+        })
+        .render(ColorOverLifetimeModifier { gradient }),
+    );
+
+    commands.spawn((
+        ParticleEffectBundle::new(effect)
+            .with_spawner(spawner),
+        Name::new("effect"),
+        ImpactEffect,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+#[allow(clippy::type_complexity)]
+fn show_ball_impacts(
+    mut events: EventReader<CollisionEvent>,
+    balls: Query<&Transform, With<Ball>>,
+    mut effect: Query<
+        (&mut ParticleEffect, &mut Transform),
+        (With<ImpactEffect>, Without<Ball>),
+    >,
+) {
+    for event in events.iter() {
+        let CollisionEvent::Started(a, b, _) = event else {
+            continue;
+        };
+        let Ok(ball_transform) =
+            balls.get(*a).or_else(|_| balls.get(*b))
+        else {
+            continue;
+        };
+        let Ok((mut effect, mut effect_transform)) =
+            effect.get_single_mut()
+        else {
+            return;
+        };
+        effect_transform.translation =
+            ball_transform.translation;
+        effect_transform.translation.z = 10.0;
+        // Spawn the particles
+        effect.maybe_spawner().unwrap().reset();
+    }
+}
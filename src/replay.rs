@@ -0,0 +1,229 @@
+//! Every tick's [`PlayerInput`] is recorded, along with
+//! what's needed to start the same game again. Since
+//! gameplay is deterministic, feeding the inputs back
+//! reproduces the run exactly, see [`play_back`].
+use std::{fs, path::Path};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config,
+    gameplay::{
+        headless_app, pick_seed, step, StartingLevel,
+    },
+    input::PlayerInput,
+    starting_new_game, Difficulty, GameSeed, GameState,
+    GameplaySet,
+};
+
+/// Bumped whenever a change to the format or to
+/// gameplay would make older replays play out
+/// differently
+pub const REPLAY_VERSION: u32 = 3;
+
+/// Recording and playback, part of
+/// [`crate::gameplay::GameplayPlugin`]
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>()
+            .add_system(
+                start_recording
+                    .after(pick_seed)
+                    .run_if(starting_new_game)
+                    .in_schedule(OnEnter(
+                        GameState::Playing,
+                    )),
+            )
+            .add_systems(
+                (
+                    play_back_input
+                        .run_if(in_state(
+                            GameState::Playing,
+                        ))
+                        .run_if(
+                            resource_exists::<Playback>(),
+                        ),
+                    record_input.run_if(in_state(
+                        GameState::Playing,
+                    )),
+                )
+                    .chain()
+                    .before(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+/// Saves the last game's replay when the game closes
+pub struct SaveReplayPlugin;
+
+impl Plugin for SaveReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            save_on_exit.in_base_set(CoreSet::Last),
+        );
+    }
+}
+
+/// Everything needed to play a game again
+#[derive(
+    Debug, Default, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub level: usize,
+    pub difficulty: Difficulty,
+    /// one entry per fixed tick, starting with the
+    /// first tick of the game
+    pub inputs: Vec<PlayerInput>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| error.to_string())?;
+        let replay: Replay = ron::from_str(&contents)
+            .map_err(|error| error.to_string())?;
+        if replay.version != REPLAY_VERSION {
+            return Err(format!(
+                "replay is version {}, expected {}",
+                replay.version, REPLAY_VERSION
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = ron::to_string(self)
+            .map_err(|error| error.to_string())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|error| error.to_string())?;
+        }
+        fs::write(path, contents)
+            .map_err(|error| error.to_string())
+    }
+
+    /// Where the last game played is saved
+    pub fn last_path() -> Option<std::path::PathBuf> {
+        config::data_dir()
+            .map(|dir| dir.join("replays").join("last.ron"))
+    }
+}
+
+/// The game in progress, restarted whenever a new game
+/// starts
+#[derive(Debug, Default, Resource)]
+pub struct Recording(pub Replay);
+
+/// Replaces the player's input with a recorded game's.
+/// The app must have been started with the replay's
+/// seed. Removed once every recorded tick has been
+/// played, handing control back to the player.
+#[derive(Debug, Resource)]
+pub struct Playback {
+    replay: Replay,
+    tick: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback { replay, tick: 0 }
+    }
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+    /// Whether every recorded tick has been played
+    pub fn finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+}
+
+/// Play a replay from start to finish without a window,
+/// returning the app as it was after the last recorded
+/// tick, or once the game ended. Used to check a run
+/// really happened.
+pub fn play_back(replay: Replay) -> App {
+    let mut app = headless_app(replay.seed);
+    app.insert_resource(StartingLevel(replay.level))
+        .insert_resource(replay.difficulty)
+        .insert_resource(Playback::new(replay))
+        .insert_resource(NextState(Some(
+            GameState::Playing,
+        )));
+    step(&mut app);
+    while app.world.contains_resource::<Playback>()
+        && app.world.resource::<State<GameState>>().0
+            == GameState::Playing
+    {
+        step(&mut app);
+    }
+    app
+}
+
+fn start_recording(
+    seed: Res<GameSeed>,
+    level: Res<StartingLevel>,
+    difficulty: Res<Difficulty>,
+    mut recording: ResMut<Recording>,
+) {
+    recording.0 = Replay {
+        version: REPLAY_VERSION,
+        seed: seed.0,
+        level: level.0,
+        difficulty: *difficulty,
+        inputs: Vec::new(),
+    };
+}
+
+fn play_back_input(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut input: ResMut<PlayerInput>,
+) {
+    *input = playback
+        .replay
+        .inputs
+        .get(playback.tick)
+        .cloned()
+        .unwrap_or_default();
+    playback.tick += 1;
+    if playback.finished() {
+        commands.remove_resource::<Playback>();
+    }
+}
+
+fn record_input(
+    input: Res<PlayerInput>,
+    mut recording: ResMut<Recording>,
+) {
+    recording.0.inputs.push(input.clone());
+}
+
+fn save_on_exit(
+    exits: EventReader<AppExit>,
+    recording: Res<Recording>,
+    playback: Option<Res<Playback>>,
+) {
+    if exits.is_empty()
+        || recording.0.inputs.is_empty()
+        || playback.is_some()
+    {
+        return;
+    }
+    let Some(path) = Replay::last_path() else {
+        return;
+    };
+    match recording.0.save(&path) {
+        Ok(()) => {
+            info!("saved replay to {}", path.display())
+        }
+        Err(error) => {
+            warn!("couldn't save replay: {error}")
+        }
+    }
+}
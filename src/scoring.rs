@@ -1,28 +1,97 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use bevy::prelude::{
-    App, IntoSystemAppConfig, OnEnter, OnExit, Plugin, Res,
-    ResMut, Resource,
+use bevy::{
+    asset::{
+        AssetLoader, FileAssetIo, LoadContext, LoadedAsset,
+    },
+    prelude::{
+        not, App, CoreSchedule, EventReader, EventWriter,
+        FixedTime, IntoSystemAppConfig,
+        IntoSystemAppConfigs, IntoSystemConfig,
+        IntoSystemConfigs, OnEnter, Or, Plugin, Query, Res,
+        ResMut, Resource, With,
+    },
+    reflect::TypeUuid,
+    utils::BoxedFuture,
 };
+use bevy_rapier2d::prelude::CollisionEvent;
+use serde::{Deserialize, Serialize};
 
-use crate::GameState;
+use crate::{
+    ball::ball_contacts,
+    blocks::{block_removal, Block, BlockDestroyed},
+    gameplay::CurrentLevel,
+    high_scores::{self, HighScoreEntry, HighScores},
+    powerups::PowerupCollected,
+    starting_new_game, Ball, DespawnArea, Difficulty,
+    GameSeed, GameState, GameplaySet, Lives, Paddle,
+    Powerup,
+};
 
 pub struct ScorePlugin;
 
 impl Plugin for ScorePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Timer>()
+            .add_event::<ScoreChanged>()
             .init_resource::<Score>()
-            .init_resource::<HighScore>()
+            .init_resource::<HighScores>()
+            .init_resource::<RunStats>()
+            .init_resource::<Combo>()
+            .add_systems(
+                (start_timer, reset_run)
+                    .distributive_run_if(starting_new_game)
+                    .in_schedule(OnEnter(
+                        GameState::Playing,
+                    )),
+            )
             .add_system(
-                start_timer.in_schedule(OnEnter(
-                    GameState::Playing,
-                )),
+                resume_timer
+                    .run_if(not(starting_new_game))
+                    .in_schedule(OnEnter(
+                        GameState::Playing,
+                    )),
             )
             .add_system(
-                close_timer.in_schedule(OnExit(
-                    GameState::Playing,
+                pause_timer.in_schedule(OnEnter(
+                    GameState::Paused,
                 )),
+            )
+            .add_systems(
+                (track_play_time, break_combo)
+                    .in_set(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    score_blocks.after(block_removal),
+                    score_powerups,
+                )
+                    .in_set(GameplaySet::React)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (close_timer, record_high_score)
+                    .chain()
+                    .in_schedule(OnEnter(
+                        GameState::GameOver,
+                    )),
+            )
+            .add_systems(
+                (
+                    award_clear_bonus,
+                    close_timer,
+                    record_high_score,
+                )
+                    .chain()
+                    .in_schedule(OnEnter(
+                        GameState::Victory,
+                    )),
             );
     }
 }
@@ -34,50 +103,335 @@ pub struct Score {
     pub score: u32,
 }
 
+/// Sent whenever points are awarded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreChanged {
+    pub points: u32,
+    /// the score after adding `points`
+    pub total: u32,
+}
+
+impl Score {
+    pub fn award(
+        &mut self,
+        points: u32,
+        changed: &mut EventWriter<ScoreChanged>,
+    ) {
+        if points == 0 {
+            return;
+        }
+        self.score += points;
+        changed.send(ScoreChanged {
+            points,
+            total: self.score,
+        });
+    }
+}
+
+/// Blocks broken in a row without a ball touching the
+/// paddle or being lost
 #[derive(
     Debug, Default, Clone, PartialEq, Eq, Resource,
 )]
-pub struct HighScore {
-    pub score: u32,
-    pub time: Duration,
+pub struct Combo(pub u32);
+
+/// How many points everything is worth. The game
+/// loads these from `assets/scoring.rules.ron` along
+/// with its other assets, see
+/// [`crate::assets::RulesAssets`]. There are no
+/// built-in values, the file is the only source.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Resource,
+    Serialize,
+    Deserialize,
+    TypeUuid,
+)]
+#[uuid = "f5d4324c-23b6-4112-b09e-01d3ec6e213a"]
+pub struct ScoringRules {
+    /// points for breaking each kind of block
+    pub blocks: BTreeMap<Block, u32>,
+    /// points for catching a powerup
+    pub powerup: u32,
+    /// points for catching a curse, see
+    /// [`Powerup::is_curse`]
+    pub curse: u32,
+    /// points for clearing a level
+    pub clear: u32,
+    /// extra clear points for every life left
+    pub clear_per_life: u32,
+    /// a level cleared faster than this earns
+    /// `clear_per_second` for every second under it
+    pub clear_par_time: Duration,
+    pub clear_per_second: u32,
+    /// the multiplier goes up by one every this many
+    /// blocks broken in a row, see [`Combo`]
+    pub combo_step: u32,
+    pub max_multiplier: u32,
+}
+
+/// Reads [`ScoringRules`] from `.rules.ron` files
+#[derive(Default)]
+pub struct ScoringRulesLoader;
+
+impl AssetLoader for ScoringRulesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>>
+    {
+        Box::pin(async move {
+            let rules: ScoringRules =
+                ron::de::from_bytes(bytes)?;
+            load_context
+                .set_default_asset(LoadedAsset::new(rules));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rules.ron"]
+    }
+}
+
+impl ScoringRules {
+    /// The rules read straight from `assets/`, for apps
+    /// that don't load assets, like
+    /// [`crate::gameplay::headless_app`]
+    pub fn load_shipped() -> Result<Self, String> {
+        ScoringRules::load_from(
+            &FileAssetIo::get_base_path()
+                .join("assets")
+                .join("scoring.rules.ron"),
+        )
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| error.to_string())?;
+        ron::from_str(&contents)
+            .map_err(|error| error.to_string())
+    }
+
+    pub fn block_points(&self, block: Block) -> u32 {
+        self.blocks.get(&block).copied().unwrap_or(0)
+    }
+
+    /// Points multiplier after `combo` blocks broken
+    /// in a row
+    pub fn multiplier(&self, combo: u32) -> u32 {
+        let steps = combo.saturating_sub(1)
+            / self.combo_step.max(1);
+        (1 + steps).min(self.max_multiplier.max(1))
+    }
+
+    /// Points for breaking `block` as the `combo`th
+    /// block in a row
+    pub fn combo_points(
+        &self,
+        block: Block,
+        combo: u32,
+    ) -> u32 {
+        self.block_points(block) * self.multiplier(combo)
+    }
+
+    pub fn powerup_points(&self, powerup: Powerup) -> u32 {
+        if powerup.is_curse() {
+            self.curse
+        } else {
+            self.powerup
+        }
+    }
+
+    /// Bonus for clearing a level with `lives` left
+    /// after `play_time` of play
+    pub fn clear_bonus(
+        &self,
+        lives: u32,
+        play_time: Duration,
+    ) -> u32 {
+        let seconds_under_par =
+            self.clear_par_time
+                .saturating_sub(play_time)
+                .as_secs() as u32;
+        self.clear
+            + self.clear_per_life * lives
+            + self.clear_per_second * seconds_under_par
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+/// What happened during the current game, shown once it
+/// ends
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, Resource,
+)]
+pub struct RunStats {
+    /// simulated play time. Unlike [`Timer`] it's the
+    /// same every time a replay is played back.
+    pub play_time: Duration,
+    pub blocks_broken: u32,
+    pub best_combo: u32,
+    /// where the game that just ended placed in the
+    /// [`HighScores`], if it made the table
+    pub high_score_rank: Option<usize>,
+}
+
+/// How long the current game has been played, not
+/// counting time spent paused
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, Resource,
+)]
 pub struct Timer {
+    /// when play last started or resumed, `None` while
+    /// paused
     pub start: Option<Instant>,
+    /// play time before the last pause
+    pub played: Duration,
+    /// the total, once the game is over
     pub runtime: Option<Duration>,
 }
 
-impl Default for Timer {
-    fn default() -> Self {
-        Timer {
-            start: None,
-            runtime: None,
-        }
+impl Timer {
+    pub fn elapsed(&self) -> Duration {
+        self.played
+            + self.start.map_or(Duration::ZERO, |start| {
+                start.elapsed()
+            })
     }
 }
 
 fn start_timer(mut timer: ResMut<Timer>) {
     *timer = Timer {
         start: Some(Instant::now()),
+        played: Duration::ZERO,
         runtime: None,
     };
 }
 
-fn close_timer(
-    mut timer: ResMut<Timer>,
-    score: Res<Score>,
-    mut high_score: ResMut<HighScore>,
+fn reset_run(
+    mut score: ResMut<Score>,
+    mut stats: ResMut<RunStats>,
+    mut combo: ResMut<Combo>,
 ) {
-    let elapsed = timer.start.unwrap().elapsed();
+    *score = Score::default();
+    *stats = RunStats::default();
+    *combo = Combo::default();
+}
+
+fn pause_timer(mut timer: ResMut<Timer>) {
+    timer.played = timer.elapsed();
+    timer.start = None;
+}
+
+fn resume_timer(mut timer: ResMut<Timer>) {
+    timer.start = Some(Instant::now());
+}
+
+fn close_timer(mut timer: ResMut<Timer>) {
+    let elapsed = timer.elapsed();
+    timer.start = None;
     timer.runtime = Some(elapsed);
-    if score.score > high_score.score
-        || score.score == high_score.score
-            && elapsed < high_score.time
-    {
-        *high_score = HighScore {
+}
+
+/// Enters the game that just ended into the
+/// [`HighScores`], unless it scored nothing. The time
+/// is the one the player saw on the [`Timer`].
+pub fn record_high_score(
+    score: Res<Score>,
+    timer: Res<Timer>,
+    level: Res<CurrentLevel>,
+    mode: Res<Difficulty>,
+    seed: Res<GameSeed>,
+    mut high_scores: ResMut<HighScores>,
+    mut stats: ResMut<RunStats>,
+) {
+    if score.score == 0 {
+        stats.high_score_rank = None;
+        return;
+    }
+    stats.high_score_rank =
+        high_scores.insert(HighScoreEntry {
+            name: None,
             score: score.score,
-            time: elapsed,
-        }
+            time: timer.runtime.unwrap_or_default(),
+            level: level.0,
+            date: high_scores::now(),
+            mode: *mode,
+            seed: seed.0,
+        });
+}
+
+fn track_play_time(
+    fixed_time: Res<FixedTime>,
+    mut stats: ResMut<RunStats>,
+) {
+    stats.play_time += fixed_time.period;
+}
+
+fn score_blocks(
+    mut destroyed: EventReader<BlockDestroyed>,
+    rules: Res<ScoringRules>,
+    mut score: ResMut<Score>,
+    mut stats: ResMut<RunStats>,
+    mut combo: ResMut<Combo>,
+    mut changed: EventWriter<ScoreChanged>,
+) {
+    for event in destroyed.iter() {
+        stats.blocks_broken += 1;
+        combo.0 += 1;
+        stats.best_combo = stats.best_combo.max(combo.0);
+        score.award(
+            rules.combo_points(event.block, combo.0),
+            &mut changed,
+        );
+    }
+}
+
+/// A ball touching the paddle, or falling past it,
+/// ends the combo
+#[allow(clippy::type_complexity)]
+fn break_combo(
+    mut events: EventReader<CollisionEvent>,
+    balls: Query<(), With<Ball>>,
+    breakers: Query<
+        (),
+        Or<(With<Paddle>, With<DespawnArea>)>,
+    >,
+    mut combo: ResMut<Combo>,
+) {
+    let broken = ball_contacts(&mut events, &balls)
+        .any(|(_, other)| breakers.contains(other));
+    if broken && combo.0 > 0 {
+        combo.0 = 0;
+    }
+}
+
+fn score_powerups(
+    mut collected: EventReader<PowerupCollected>,
+    rules: Res<ScoringRules>,
+    mut score: ResMut<Score>,
+    mut changed: EventWriter<ScoreChanged>,
+) {
+    for PowerupCollected(powerup) in collected.iter() {
+        score.award(
+            rules.powerup_points(*powerup),
+            &mut changed,
+        );
     }
 }
+
+fn award_clear_bonus(
+    rules: Res<ScoringRules>,
+    lives: Res<Lives>,
+    stats: Res<RunStats>,
+    mut score: ResMut<Score>,
+    mut changed: EventWriter<ScoreChanged>,
+) {
+    score.award(
+        rules.clear_bonus(lives.0, stats.play_time),
+        &mut changed,
+    );
+}
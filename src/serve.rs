@@ -0,0 +1,228 @@
+//! Every serve starts with the ball resting on the
+//! paddle. It follows the paddle around until the
+//! player launches it, or the auto launch timer runs
+//! out.
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    ball::BallSpeed, custom_commands::ServeBall,
+    input::PlayerInput, paddle::PADDLE_HEIGHT, Ball,
+    ConnectToPaddle, GameState, GameplaySet, Lives, Paddle,
+};
+
+pub struct ServePlugin;
+
+impl Plugin for ServePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ServeSettings>()
+            .add_systems(
+                // a launched ball must not be pulled back
+                // onto the paddle
+                (follow_paddle, launch_serve)
+                    .chain()
+                    .in_set(GameplaySet::Tick)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                serve_after_life_lost
+                    .in_set(GameplaySet::React)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct ServeSettings {
+    /// the ball launches on its own after this long
+    pub auto_launch: Duration,
+    /// the aim sweeps back and forth between plus and
+    /// minus this angle from vertical, in radians
+    pub max_aim: f32,
+    /// how fast the aim sweeps, in radians per second
+    pub aim_speed: f32,
+}
+
+impl Default for ServeSettings {
+    fn default() -> Self {
+        ServeSettings {
+            auto_launch: Duration::from_secs(5),
+            max_aim: 50_f32.to_radians(),
+            aim_speed: 2.0,
+        }
+    }
+}
+
+/// A ball waiting on the paddle to be launched
+#[derive(Component, Debug)]
+pub struct Serving {
+    pub auto_launch: Timer,
+}
+
+impl Serving {
+    pub fn new(settings: &ServeSettings) -> Self {
+        Serving {
+            auto_launch: Timer::new(
+                settings.auto_launch,
+                TimerMode::Once,
+            ),
+        }
+    }
+    /// Current aim in radians from vertical, positive
+    /// is to the left
+    pub fn aim(&self, settings: &ServeSettings) -> f32 {
+        settings.max_aim
+            * (self.auto_launch.elapsed_secs()
+                * settings.aim_speed)
+                .sin()
+    }
+}
+
+/// Where a connected ball sits relative to the paddle
+pub fn serve_position(
+    paddle: &Transform,
+    connection: &ConnectToPaddle,
+) -> Vec3 {
+    Vec3::new(
+        paddle.translation.x + connection.diff,
+        // half the paddle, the ball radius and a
+        // little gap so they don't touch
+        paddle.translation.y + PADDLE_HEIGHT / 2.0 + 11.0,
+        5.0,
+    )
+}
+
+/// Arrow showing which way a serve will launch
+#[derive(Component)]
+pub(crate) struct AimIndicator;
+
+#[allow(clippy::type_complexity)]
+fn follow_paddle(
+    paddles: Query<&Transform, With<Paddle>>,
+    mut balls: Query<
+        (&ConnectToPaddle, &mut Transform, &mut Velocity),
+        (With<Ball>, Without<Paddle>),
+    >,
+) {
+    let Ok(paddle) = paddles.get_single() else {
+        return;
+    };
+    for (connection, mut transform, mut velocity) in
+        balls.iter_mut()
+    {
+        transform.translation =
+            serve_position(paddle, connection);
+        *velocity = Velocity::zero();
+    }
+}
+
+fn launch_serve(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    input: Res<PlayerInput>,
+    settings: Res<ServeSettings>,
+    ball_speed: Res<BallSpeed>,
+    mut balls: Query<
+        (Entity, &mut Serving, &mut Velocity),
+        With<Ball>,
+    >,
+) {
+    let launch = input.launch;
+    for (entity, mut serving, mut velocity) in
+        balls.iter_mut()
+    {
+        serving.auto_launch.tick(fixed_time.period);
+        if launch || serving.auto_launch.finished() {
+            let direction =
+                Vec2::from_angle(serving.aim(&settings))
+                    .rotate(Vec2::Y);
+            *velocity = Velocity::linear(
+                ball_speed.apply(direction),
+            );
+            commands
+                .entity(entity)
+                .remove::<(Serving, ConnectToPaddle)>();
+        }
+    }
+}
+
+/// When the last ball is gone the player loses a life
+/// and, if they have any left, gets a new serve.
+/// Otherwise the game is over.
+fn serve_after_life_lost(
+    mut commands: Commands,
+    balls: Query<(), With<Ball>>,
+    mut lives: ResMut<Lives>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !balls.is_empty() || lives.0 == 0 {
+        return;
+    }
+    lives.0 -= 1;
+    if lives.0 > 0 {
+        commands.add(ServeBall);
+    } else {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+pub(crate) fn show_aim_indicator(
+    mut commands: Commands,
+    settings: Res<ServeSettings>,
+    serving: Query<&Serving>,
+    new_serves: Query<Entity, Added<Serving>>,
+    mut indicators: Query<
+        (Entity, &Parent, &mut Transform),
+        With<AimIndicator>,
+    >,
+) {
+    for (entity, parent, mut transform) in
+        indicators.iter_mut()
+    {
+        match serving.get(parent.get()) {
+            Ok(serving) => {
+                transform.rotation = Quat::from_rotation_z(
+                    serving.aim(&settings),
+                );
+            }
+            Err(_) => {
+                commands.entity(entity).despawn_recursive()
+            }
+        }
+    }
+    for entity in new_serves.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                ShapeBundle {
+                    path: aim_indicator_path(),
+                    ..default()
+                },
+                Stroke::new(
+                    Color::rgba(1.0, 1.0, 1.0, 0.8),
+                    3.0,
+                ),
+                AimIndicator,
+            ));
+        });
+    }
+}
+
+/// An arrow pointing straight up from the edge of the
+/// ball
+fn aim_indicator_path() -> Path {
+    let tip = Vec2::new(0.0, 90.0);
+    let mut builder = PathBuilder::new();
+    builder.move_to(Vec2::new(0.0, 16.0));
+    builder.line_to(tip);
+    for side in [-1.0, 1.0] {
+        builder.move_to(tip);
+        builder.line_to(
+            tip + Vec2::from_angle(side * PI / 6.0)
+                .rotate(Vec2::new(0.0, -14.0)),
+        );
+    }
+    builder.build()
+}
@@ -1,19 +1,96 @@
 use bevy::{app::AppExit, prelude::*};
 
-use crate::GameState;
+use crate::{
+    assets::{fonts_loaded, FontAssets},
+    custom_commands::{RestartGame, RestartLevel},
+    high_scores::HighScores,
+    input::{
+        Action, Actions, Binding, Bindings, PaddleControl,
+    },
+    scoring::{record_high_score, RunStats, Score, Timer},
+    Difficulty, GameState, StateScoped,
+};
+
+/// Longest name that can go on the high score
+const MAX_NAME_LENGTH: usize = 12;
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_startup_system(game_ui).add_systems((
-            button_new_game_system,
-            button_exit_system,
-            on_game_state_change,
-        ));
+        app.init_resource::<Rebinding>()
+            // these need the loaded font, which doesn't
+            // exist if loading failed
+            .add_systems(
+                (game_ui, bindings_ui, pause_ui)
+                    .distributive_run_if(fonts_loaded)
+                    .in_schedule(OnExit(
+                        GameState::Loading,
+                    )),
+            )
+            .add_systems((
+                toggle_pause.before(capture_binding),
+                button_pause_menu_system,
+                button_new_game_system,
+                button_exit_system,
+                button_controls_system,
+                button_difficulty_system,
+                button_bindings_system,
+                button_high_scores_system
+                    .run_if(fonts_loaded),
+                button_high_scores_back_system,
+                button_rebind_system,
+                button_bindings_back_system,
+                capture_binding.after(button_rebind_system),
+                update_binding_labels
+                    .after(capture_binding),
+                on_game_state_change,
+                gamepad_menu_navigation,
+            ))
+            .init_resource::<NameEntry>()
+            .add_system(
+                end_screen
+                    .after(record_high_score)
+                    .in_schedule(OnEnter(
+                        GameState::GameOver,
+                    )),
+            )
+            .add_system(
+                end_screen
+                    .after(record_high_score)
+                    .in_schedule(OnEnter(
+                        GameState::Victory,
+                    )),
+            )
+            .add_systems((
+                button_end_screen_system,
+                type_name.after(button_end_screen_system),
+            ));
     }
 }
 
+/// Buttons of type `B` whose [`Interaction`] changed
+/// this frame
+type ButtonInteractions<'w, 's, B> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<B>),
+>;
+
+/// Like [`ButtonInteractions`], with the children so
+/// the button can change its label
+type LabeledButtonInteractions<'w, 's, B> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        &'static Children,
+    ),
+    (Changed<Interaction>, With<B>),
+>;
+
 const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const PRESSED_GOOD_BUTTON: Color =
@@ -27,13 +104,111 @@ struct ButtonNewGame;
 #[derive(Debug, Component)]
 struct ButtonExit;
 
+#[derive(Debug, Component)]
+struct ButtonControls;
+
+/// Cycles through the [`Difficulty`] levels
+#[derive(Debug, Component)]
+struct ButtonDifficulty;
+
+#[derive(Debug, Component)]
+struct ButtonBindings;
+
+#[derive(Debug, Component)]
+struct ButtonBindingsBack;
+
+#[derive(Debug, Component)]
+struct ButtonHighScores;
+
+#[derive(Debug, Component)]
+struct ButtonHighScoresBack;
+
+/// Rebinds its action when clicked, see
+/// [`capture_binding`]
+#[derive(Debug, Component)]
+struct RebindButton(Action);
+
 #[derive(Debug, Component)]
 struct Menu;
 
-fn game_ui(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-) {
+/// Shown while the game is paused
+#[derive(Debug, Component)]
+struct PauseMenu;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum PauseButton {
+    Resume,
+    RestartLevel,
+    Settings,
+    QuitToMenu,
+}
+
+impl PauseButton {
+    fn label(&self) -> &'static str {
+        match self {
+            PauseButton::Resume => "Resume",
+            PauseButton::RestartLevel => "Restart Level",
+            PauseButton::Settings => "Settings",
+            PauseButton::QuitToMenu => "Quit to Menu",
+        }
+    }
+}
+
+/// The buttons on the game over and victory screens
+#[derive(Debug, Component)]
+struct EndScreenButtons;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum EndScreenButton {
+    Retry,
+    MainMenu,
+    EnterName,
+}
+
+impl EndScreenButton {
+    fn label(&self) -> &'static str {
+        match self {
+            EndScreenButton::Retry => "Retry",
+            EndScreenButton::MainMenu => "Main Menu",
+            EndScreenButton::EnterName => "Enter Name",
+        }
+    }
+}
+
+/// Shows the name being typed for the high score
+#[derive(Debug, Component)]
+struct NameEntryText;
+
+/// The name being typed for the high score, `None`
+/// when the player isn't typing
+#[derive(Debug, Default, Resource)]
+struct NameEntry(Option<String>);
+
+/// Lists the [`HighScores`]. Spawned from the menu
+/// each time it's opened, so it's never out of date.
+#[derive(Debug, Component)]
+struct HighScoresScreen;
+
+/// The buttons on the high score screen
+#[derive(Debug, Component)]
+struct HighScoresButtons;
+
+/// The screen listing every [`Action`]'s bindings
+#[derive(Debug, Component)]
+struct BindingsMenu;
+
+/// The action waiting for the player to press the
+/// button it should be bound to
+#[derive(Debug, Default, Resource)]
+struct Rebinding {
+    action: Option<Action>,
+    /// false during the frame the rebind button was
+    /// pressed, so that press isn't taken as the new
+    /// binding
+    armed: bool,
+}
+
+fn game_ui(mut commands: Commands, fonts: Res<FontAssets>) {
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -69,18 +244,46 @@ fn game_ui(
                     parent.spawn(TextBundle::from_section(
                         "Button",
                         TextStyle {
-                            font: asset_server.load(
-                                "fonts/AlfaSlabOne-Regular.ttf",
+                            font: fonts.main.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb(
+                                0.9, 0.9, 0.9,
                             ),
+                        },
+                    ));
+                })
+                .insert(ButtonNewGame);
+
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Px(250.0),
+                            Val::Px(250.0),
+                        ),
+                        justify_content:
+                            JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Button",
+                        TextStyle {
+                            font: fonts.main.clone(),
                             font_size: 40.0,
                             color: Color::rgb(
                                 0.9, 0.9, 0.9,
                             ),
                         },
                     ));
-                }).insert(ButtonNewGame);
+                })
+                .insert(ButtonExit);
 
-                parent
+            parent
                 .spawn(ButtonBundle {
                     style: Style {
                         size: Size::new(
@@ -99,30 +302,241 @@ fn game_ui(
                     parent.spawn(TextBundle::from_section(
                         "Button",
                         TextStyle {
-                            font: asset_server.load(
-                                "fonts/AlfaSlabOne-Regular.ttf",
+                            font: fonts.main.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb(
+                                0.9, 0.9, 0.9,
+                            ),
+                        },
+                    ));
+                })
+                .insert(ButtonControls);
+
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Px(250.0),
+                            Val::Px(250.0),
+                        ),
+                        justify_content:
+                            JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Normal",
+                        TextStyle {
+                            font: fonts.main.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb(
+                                0.9, 0.9, 0.9,
+                            ),
+                        },
+                    ));
+                })
+                .insert(ButtonDifficulty);
+
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Px(250.0),
+                            Val::Px(250.0),
+                        ),
+                        justify_content:
+                            JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Bindings",
+                        TextStyle {
+                            font: fonts.main.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb(
+                                0.9, 0.9, 0.9,
                             ),
+                        },
+                    ));
+                })
+                .insert(ButtonBindings);
+
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Px(250.0),
+                            Val::Px(250.0),
+                        ),
+                        justify_content:
+                            JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "High Scores",
+                        TextStyle {
+                            font: fonts.main.clone(),
                             font_size: 40.0,
                             color: Color::rgb(
                                 0.9, 0.9, 0.9,
                             ),
                         },
                     ));
-                }).insert(ButtonExit);
-        }).insert(Menu);
+                })
+                .insert(ButtonHighScores);
+        })
+        .insert(Menu);
+}
+
+fn pause_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+) {
+    let text_style = TextStyle {
+        font: fonts.main.clone(),
+        font_size: 40.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(
+                    Val::Percent(100.0),
+                    Val::Percent(100.0),
+                ),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                gap: Size::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: Color::rgba(
+                0.0, 0.0, 0.0, 0.5,
+            )
+            .into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .with_children(|parent| {
+            for button in [
+                PauseButton::Resume,
+                PauseButton::RestartLevel,
+                PauseButton::Settings,
+                PauseButton::QuitToMenu,
+            ] {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(
+                                Val::Px(400.0),
+                                Val::Px(80.0),
+                            ),
+                            justify_content:
+                                JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: NORMAL_BUTTON
+                            .into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(
+                                button.label(),
+                                text_style.clone(),
+                            ),
+                        );
+                    })
+                    .insert(button);
+            }
+        })
+        .insert(PauseMenu);
+}
+
+/// One button per action, then a way back. Every child
+/// is a button so [`gamepad_menu_navigation`] can move
+/// through them.
+fn bindings_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+) {
+    let text_style = TextStyle {
+        font: fonts.main.clone(),
+        font_size: 30.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let button = || ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(700.0), Val::Px(60.0)),
+            justify_content: JustifyContent::FlexStart,
+            align_items: AlignItems::Center,
+            padding: UiRect::horizontal(Val::Px(20.0)),
+            ..default()
+        },
+        background_color: NORMAL_BUTTON.into(),
+        ..default()
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(
+                    Val::Percent(100.0),
+                    Val::Percent(100.0),
+                ),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                gap: Size::all(Val::Px(10.0)),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .with_children(|parent| {
+            for action in Action::ALL {
+                parent
+                    .spawn(button())
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(
+                                action.label(),
+                                text_style.clone(),
+                            ),
+                        );
+                    })
+                    .insert(RebindButton(action));
+            }
+            parent
+                .spawn(button())
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Back",
+                        text_style.clone(),
+                    ));
+                })
+                .insert(ButtonBindingsBack);
+        })
+        .insert(BindingsMenu);
 }
 
 fn button_new_game_system(
-    mut interaction_query: Query<
-        (
-            &Interaction,
-            &mut BackgroundColor,
-            &Children,
-        ),
-        (
-            Changed<Interaction>,
-            With<ButtonNewGame>,
-        ),
+    mut interaction_query: LabeledButtonInteractions<
+        ButtonNewGame,
     >,
     mut text_query: Query<&mut Text>,
     mut next_state: ResMut<NextState<GameState>>,
@@ -154,13 +568,8 @@ fn button_new_game_system(
     }
 }
 fn button_exit_system(
-    mut interaction_query: Query<
-        (
-            &Interaction,
-            &mut BackgroundColor,
-            &Children,
-        ),
-        (Changed<Interaction>, With<ButtonExit>),
+    mut interaction_query: LabeledButtonInteractions<
+        ButtonExit,
     >,
     mut exit: EventWriter<AppExit>,
     mut text_query: Query<&mut Text>,
@@ -189,21 +598,859 @@ fn button_exit_system(
     }
 }
 
-fn on_game_state_change(
-    game_state: Res<State<GameState>>,
-    mut game_menu: Query<&mut Visibility, With<Menu>>,
+/// Switches the paddle between keyboard and mouse
+fn button_controls_system(
+    mut interaction_query: LabeledButtonInteractions<
+        ButtonControls,
+    >,
+    mut text_query: Query<&mut Text>,
+    mut control: ResMut<PaddleControl>,
 ) {
-    if game_state.is_changed() {
-        for mut game_menu in game_menu.iter_mut() {
-            match game_state.0 {
-                GameState::Menu => {
-                    *game_menu = Visibility::Visible
-                }
-                GameState::Playing => {
-                    *game_menu = Visibility::Hidden
-                }
-                GameState::Paused => {
-                    *game_menu = Visibility::Visible
+    for (interaction, mut color, children) in
+        &mut interaction_query
+    {
+        let mut text =
+            text_query.get_mut(children[0]).unwrap();
+        match *interaction {
+            Interaction::Clicked => {
+                *control = match *control {
+                    PaddleControl::Keyboard => {
+                        PaddleControl::Mouse
+                    }
+                    PaddleControl::Mouse => {
+                        PaddleControl::Keyboard
+                    }
+                };
+                *color = PRESSED_GOOD_BUTTON.into();
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+        text.sections[0].value = match *control {
+            PaddleControl::Keyboard => "Keyboard",
+            PaddleControl::Mouse => "Mouse",
+        }
+        .to_string();
+    }
+}
+
+/// Changes the difficulty of the next game
+fn button_difficulty_system(
+    mut interaction_query: LabeledButtonInteractions<
+        ButtonDifficulty,
+    >,
+    mut text_query: Query<&mut Text>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    for (interaction, mut color, children) in
+        &mut interaction_query
+    {
+        let mut text =
+            text_query.get_mut(children[0]).unwrap();
+        match *interaction {
+            Interaction::Clicked => {
+                *difficulty = difficulty.next();
+                *color = PRESSED_GOOD_BUTTON.into();
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+        text.sections[0].value =
+            difficulty.label().to_string();
+    }
+}
+
+fn button_bindings_system(
+    mut interaction_query: ButtonInteractions<
+        ButtonBindings,
+    >,
+    mut menus: Query<&mut Visibility, With<Menu>>,
+    mut bindings_menus: Query<
+        &mut Visibility,
+        (With<BindingsMenu>, Without<Menu>),
+    >,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_GOOD_BUTTON.into();
+                for mut menu in menus.iter_mut() {
+                    *menu = Visibility::Hidden;
+                }
+                for mut menu in bindings_menus.iter_mut() {
+                    *menu = Visibility::Visible;
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn button_high_scores_system(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    high_scores: Res<HighScores>,
+    mut interaction_query: ButtonInteractions<
+        ButtonHighScores,
+    >,
+    mut menus: Query<&mut Visibility, With<Menu>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_GOOD_BUTTON.into();
+                for mut menu in menus.iter_mut() {
+                    *menu = Visibility::Hidden;
+                }
+                high_scores_screen(
+                    &mut commands,
+                    &fonts,
+                    &high_scores,
+                );
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn high_scores_screen(
+    commands: &mut Commands,
+    fonts: &FontAssets,
+    high_scores: &HighScores,
+) {
+    let text_style = TextStyle {
+        font: fonts.main.clone(),
+        font_size: 30.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let mut lines: Vec<String> = high_scores
+        .0
+        .iter()
+        .enumerate()
+        .map(|(rank, entry)| {
+            format!(
+                "{}. {}  {}  {}:{:02}  level {}  {:?}  {}  \
+                 seed {}",
+                rank + 1,
+                entry.name.as_deref().unwrap_or("???"),
+                entry.score,
+                entry.time.as_secs() / 60,
+                entry.time.as_secs() % 60,
+                entry.level,
+                entry.mode,
+                entry.date_label(),
+                entry.seed,
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("No games played yet".to_string());
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(
+                    Val::Percent(100.0),
+                    Val::Percent(100.0),
+                ),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                gap: Size::all(Val::Px(10.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .insert((
+            HighScoresScreen,
+            StateScoped(GameState::Menu),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "High Scores",
+                TextStyle {
+                    font_size: 60.0,
+                    ..text_style.clone()
+                },
+            ));
+            for line in lines {
+                parent.spawn(TextBundle::from_section(
+                    line,
+                    text_style.clone(),
+                ));
+            }
+            parent
+                .spawn(NodeBundle::default())
+                .insert(HighScoresButtons)
+                .with_children(|parent| {
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                size: Size::new(
+                                    Val::Px(300.0),
+                                    Val::Px(80.0),
+                                ),
+                                justify_content:
+                                    JustifyContent::Center,
+                                align_items:
+                                    AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: NORMAL_BUTTON
+                                .into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(
+                                TextBundle::from_section(
+                                    "Back",
+                                    text_style.clone(),
+                                ),
+                            );
+                        })
+                        .insert(ButtonHighScoresBack);
+                });
+        });
+}
+
+fn button_high_scores_back_system(
+    mut commands: Commands,
+    mut interaction_query: ButtonInteractions<
+        ButtonHighScoresBack,
+    >,
+    screens: Query<Entity, With<HighScoresScreen>>,
+    mut menus: Query<&mut Visibility, With<Menu>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_GOOD_BUTTON.into();
+                for entity in screens.iter() {
+                    commands
+                        .entity(entity)
+                        .despawn_recursive();
+                }
+                for mut menu in menus.iter_mut() {
+                    *menu = Visibility::Visible;
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Returns to the pause menu if the game is paused,
+/// otherwise the main menu
+#[allow(clippy::type_complexity)]
+fn button_bindings_back_system(
+    mut interaction_query: ButtonInteractions<
+        ButtonBindingsBack,
+    >,
+    game_state: Res<State<GameState>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut menus: Query<
+        (&mut Visibility, Option<&PauseMenu>),
+        Or<(With<Menu>, With<PauseMenu>)>,
+    >,
+    mut bindings_menus: Query<
+        &mut Visibility,
+        (
+            With<BindingsMenu>,
+            Without<Menu>,
+            Without<PauseMenu>,
+        ),
+    >,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_GOOD_BUTTON.into();
+                *rebinding = Rebinding::default();
+                let paused =
+                    game_state.0 == GameState::Paused;
+                for (mut menu, pause_menu) in
+                    menus.iter_mut()
+                {
+                    if pause_menu.is_some() == paused {
+                        *menu = Visibility::Visible;
+                    }
+                }
+                for mut menu in bindings_menus.iter_mut() {
+                    *menu = Visibility::Hidden;
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Escape or start pauses and resumes, unless a button
+/// is being rebound
+fn toggle_pause(
+    actions: Actions,
+    rebinding: Res<Rebinding>,
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if rebinding.action.is_some()
+        || !actions.just_pressed(Action::Pause)
+    {
+        return;
+    }
+    match game_state.0 {
+        GameState::Playing => {
+            next_state.set(GameState::Paused)
+        }
+        GameState::Paused => {
+            next_state.set(GameState::Playing)
+        }
+        _ => {}
+    }
+}
+
+fn button_pause_menu_system(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &PauseButton),
+        Changed<Interaction>,
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut pause_menus: Query<
+        &mut Visibility,
+        With<PauseMenu>,
+    >,
+    mut bindings_menus: Query<
+        &mut Visibility,
+        (With<BindingsMenu>, Without<PauseMenu>),
+    >,
+) {
+    for (interaction, mut color, button) in
+        &mut interaction_query
+    {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_GOOD_BUTTON.into();
+                match button {
+                    PauseButton::Resume => {
+                        next_state.set(GameState::Playing)
+                    }
+                    PauseButton::RestartLevel => {
+                        commands.add(RestartLevel)
+                    }
+                    PauseButton::Settings => {
+                        for mut menu in
+                            pause_menus.iter_mut()
+                        {
+                            *menu = Visibility::Hidden;
+                        }
+                        for mut menu in
+                            bindings_menus.iter_mut()
+                        {
+                            *menu = Visibility::Visible;
+                        }
+                    }
+                    PauseButton::QuitToMenu => {
+                        next_state.set(GameState::Menu)
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Sums up the game that just ended. Scoped to the end
+/// state, so it goes away with it.
+fn end_screen(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    game_state: Res<State<GameState>>,
+    score: Res<Score>,
+    timer: Res<Timer>,
+    stats: Res<RunStats>,
+) {
+    let font = fonts.main.clone();
+    let text_style = TextStyle {
+        font,
+        font_size: 40.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let title = match game_state.0 {
+        GameState::Victory => "Victory!",
+        _ => "Game Over",
+    };
+    let time = timer.runtime.unwrap_or_default();
+    let mut summary = vec![
+        format!("Score: {}", score.score),
+        format!(
+            "Time: {}:{:02}",
+            time.as_secs() / 60,
+            time.as_secs() % 60
+        ),
+        format!("Blocks broken: {}", stats.blocks_broken),
+        format!("Best combo: {}", stats.best_combo),
+    ];
+    if let Some(rank) = stats.high_score_rank {
+        summary
+            .push(format!("New high score! #{}", rank + 1));
+    }
+    let mut buttons = vec![
+        EndScreenButton::Retry,
+        EndScreenButton::MainMenu,
+    ];
+    if stats.high_score_rank.is_some() {
+        buttons.push(EndScreenButton::EnterName);
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(
+                    Val::Percent(100.0),
+                    Val::Percent(100.0),
+                ),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                gap: Size::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: Color::rgba(
+                0.0, 0.0, 0.0, 0.5,
+            )
+            .into(),
+            ..default()
+        })
+        .insert(StateScoped(game_state.0))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 80.0,
+                    ..text_style.clone()
+                },
+            ));
+            for line in summary {
+                parent.spawn(TextBundle::from_section(
+                    line,
+                    text_style.clone(),
+                ));
+            }
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    text_style.clone(),
+                ))
+                .insert(NameEntryText);
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        gap: Size::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(EndScreenButtons)
+                .with_children(|parent| {
+                    for button in buttons {
+                        parent
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(
+                                        Val::Px(300.0),
+                                        Val::Px(80.0),
+                                    ),
+                                    justify_content:
+                                        JustifyContent::Center,
+                                    align_items:
+                                        AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color:
+                                    NORMAL_BUTTON.into(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn(
+                                    TextBundle::from_section(
+                                        button.label(),
+                                        text_style.clone(),
+                                    ),
+                                );
+                            })
+                            .insert(button);
+                    }
+                });
+        });
+}
+
+fn button_end_screen_system(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &EndScreenButton,
+        ),
+        Changed<Interaction>,
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut name_entry: ResMut<NameEntry>,
+) {
+    for (interaction, mut color, button) in
+        &mut interaction_query
+    {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_GOOD_BUTTON.into();
+                name_entry.0 = None;
+                match button {
+                    EndScreenButton::Retry => {
+                        commands.add(RestartGame)
+                    }
+                    EndScreenButton::MainMenu => {
+                        next_state.set(GameState::Menu)
+                    }
+                    EndScreenButton::EnterName => {
+                        name_entry.0 = Some(String::new())
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Types the high score's name. Enter saves it, escape
+/// gives up.
+fn type_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut name_entry: ResMut<NameEntry>,
+    stats: Res<RunStats>,
+    mut high_scores: ResMut<HighScores>,
+    mut texts: Query<&mut Text, With<NameEntryText>>,
+) {
+    let Some(name) = name_entry.0.as_mut() else {
+        characters.clear();
+        return;
+    };
+    for character in characters.iter() {
+        if !character.char.is_control()
+            && name.chars().count() < MAX_NAME_LENGTH
+        {
+            name.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+    let label = if keys.just_pressed(KeyCode::Return) {
+        let name = name.trim().to_string();
+        let label = format!("Saved as {name}");
+        match stats.high_score_rank {
+            Some(rank) if !name.is_empty() => {
+                high_scores.0[rank].name = Some(name);
+            }
+            _ => {}
+        }
+        name_entry.0 = None;
+        label
+    } else if keys.just_pressed(KeyCode::Escape) {
+        name_entry.0 = None;
+        String::new()
+    } else {
+        format!("Name: {name}_")
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
+
+fn button_rebind_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &RebindButton),
+        Changed<Interaction>,
+    >,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (interaction, mut color, RebindButton(action)) in
+        &mut interaction_query
+    {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_GOOD_BUTTON.into();
+                *rebinding = Rebinding {
+                    action: Some(*action),
+                    armed: false,
+                };
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Binds the next button pressed on any device to the
+/// action being rebound. Escape cancels.
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    if !rebinding.armed {
+        rebinding.armed = true;
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        *rebinding = Rebinding::default();
+        return;
+    }
+    let binding = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            mouse
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Mouse(*button))
+        })
+        .or_else(|| {
+            gamepad_buttons.get_just_pressed().next().map(
+                |button| {
+                    Binding::Gamepad(button.button_type)
+                },
+            )
+        });
+    if let Some(binding) = binding {
+        bindings.rebind(action, binding);
+        *rebinding = Rebinding::default();
+    }
+}
+
+fn update_binding_labels(
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(&RebindButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (RebindButton(action), children) in buttons.iter() {
+        let mut text =
+            text_query.get_mut(children[0]).unwrap();
+        text.sections[0].value = if rebinding.action
+            == Some(*action)
+        {
+            format!("{}: press a button", action.label())
+        } else {
+            let bound: Vec<String> = bindings
+                .get(*action)
+                .iter()
+                .map(Binding::label)
+                .collect();
+            format!(
+                "{}: {}",
+                action.label(),
+                bound.join(", ")
+            )
+        };
+    }
+}
+
+#[derive(Debug, Default)]
+struct MenuFocus {
+    /// the highlighted button
+    index: usize,
+    /// the button pressed last frame, released on the
+    /// next
+    pressed: Option<Entity>,
+}
+
+/// Gamepads move a highlight along the menu buttons
+/// with the d-pad and press the highlighted one with
+/// the south button. The press goes through
+/// [`Interaction`] so the button systems above handle
+/// it like a click.
+#[allow(clippy::type_complexity)]
+fn gamepad_menu_navigation(
+    game_state: Res<State<GameState>>,
+    rebinding: Res<Rebinding>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    menus: Query<
+        (&Children, &Visibility),
+        Or<(
+            With<Menu>,
+            With<PauseMenu>,
+            With<BindingsMenu>,
+            With<EndScreenButtons>,
+            With<HighScoresButtons>,
+        )>,
+    >,
+    mut buttons: Query<
+        (&mut Interaction, &mut BackgroundColor),
+        With<Button>,
+    >,
+    mut focus: Local<MenuFocus>,
+) {
+    // release last frame's press
+    if let Some(entity) = focus.pressed.take() {
+        if let Ok((mut interaction, _)) =
+            buttons.get_mut(entity)
+        {
+            *interaction = Interaction::None;
+        }
+    }
+    // the d-pad and south button are being bound
+    if game_state.0 == GameState::Playing
+        || rebinding.action.is_some()
+    {
+        return;
+    }
+    let Some((menu, _)) =
+        menus.iter().find(|(_, visibility)| {
+            **visibility != Visibility::Hidden
+        })
+    else {
+        return;
+    };
+    if menu.is_empty() {
+        return;
+    }
+    for gamepad in gamepads.iter() {
+        let just_pressed = |button_type| {
+            gamepad_buttons.just_pressed(
+                GamepadButton::new(gamepad, button_type),
+            )
+        };
+        let step =
+            if just_pressed(GamepadButtonType::DPadRight) {
+                1
+            } else if just_pressed(
+                GamepadButtonType::DPadLeft,
+            ) {
+                menu.len() - 1
+            } else {
+                0
+            };
+        if step != 0 {
+            focus.index = (focus.index + step) % menu.len();
+            for (index, entity) in menu.iter().enumerate() {
+                if let Ok((_, mut color)) =
+                    buttons.get_mut(*entity)
+                {
+                    *color = if index == focus.index {
+                        HOVERED_BUTTON.into()
+                    } else {
+                        NORMAL_BUTTON.into()
+                    };
+                }
+            }
+        }
+        if just_pressed(GamepadButtonType::South) {
+            let entity = menu[focus.index % menu.len()];
+            if let Ok((mut interaction, _)) =
+                buttons.get_mut(entity)
+            {
+                *interaction = Interaction::Clicked;
+                focus.pressed = Some(entity);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn on_game_state_change(
+    game_state: Res<State<GameState>>,
+    mut game_menu: Query<&mut Visibility, With<Menu>>,
+    mut pause_menus: Query<
+        &mut Visibility,
+        (With<PauseMenu>, Without<Menu>),
+    >,
+    mut bindings_menus: Query<
+        &mut Visibility,
+        (
+            With<BindingsMenu>,
+            Without<Menu>,
+            Without<PauseMenu>,
+        ),
+    >,
+) {
+    if game_state.is_changed() {
+        for mut bindings_menu in bindings_menus.iter_mut() {
+            *bindings_menu = Visibility::Hidden;
+        }
+        for mut pause_menu in pause_menus.iter_mut() {
+            *pause_menu = match game_state.0 {
+                GameState::Paused => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+        for mut game_menu in game_menu.iter_mut() {
+            match game_state.0 {
+                GameState::Menu => {
+                    *game_menu = Visibility::Visible
+                }
+                GameState::Playing => {
+                    *game_menu = Visibility::Hidden
+                }
+                GameState::Loading
+                | GameState::LoadingFailed
+                | GameState::Paused
+                | GameState::GameOver
+                | GameState::Victory => {
+                    *game_menu = Visibility::Hidden
                 }
             }
         }
@@ -0,0 +1,174 @@
+//! The high score table keeps the best games in order
+//! and survives being saved, edited and corrupted.
+use std::{fs, path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+use block_breaker::{
+    gameplay::{headless_app, step, CurrentLevel},
+    high_scores::{
+        HighScoreEntry, HighScores, HIGH_SCORES_VERSION,
+        MAX_HIGH_SCORES,
+    },
+    scoring::{RunStats, Score},
+    Ball, GameState, Lives,
+};
+
+fn entry(score: u32, seconds: u64) -> HighScoreEntry {
+    HighScoreEntry {
+        name: Some(format!("p{score}")),
+        score,
+        time: Duration::from_secs(seconds),
+        level: 1,
+        date: 1_760_832_000,
+        seed: 7,
+        ..default()
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "block_breaker_{name}_{}.ron",
+        std::process::id()
+    ))
+}
+
+#[test]
+fn the_table_keeps_the_best_games_in_order() {
+    let mut high_scores = HighScores::default();
+    for score in 0..MAX_HIGH_SCORES as u32 + 2 {
+        high_scores.insert(entry(score * 100, 60));
+    }
+    assert_eq!(high_scores.0.len(), MAX_HIGH_SCORES);
+    assert_eq!(high_scores.best().unwrap().score, 1100);
+    assert!(high_scores
+        .0
+        .windows(2)
+        .all(|pair| !pair[1].beats(&pair[0])));
+
+    // a faster time wins a tie
+    assert_eq!(
+        high_scores.insert(entry(1100, 30)),
+        Some(0)
+    );
+    // and too low a score doesn't make it
+    assert_eq!(high_scores.insert(entry(50, 1)), None);
+}
+
+#[test]
+fn the_table_survives_a_round_trip_through_a_file() {
+    let mut high_scores = HighScores::default();
+    high_scores.insert(entry(500, 90));
+    high_scores.insert(entry(700, 120));
+    let path = temp_path("high_scores");
+    high_scores.save_to(&path).unwrap();
+    let loaded = HighScores::load_from(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, high_scores);
+    assert_eq!(
+        loaded.best().unwrap().date_label(),
+        "2025-10-19"
+    );
+}
+
+#[test]
+fn unreadable_tables_are_set_aside() {
+    let path = temp_path("corrupt_high_scores");
+    fs::write(&path, "(version: 1, entries: [(sco")
+        .unwrap();
+    let loaded = HighScores::load_from(&path);
+    assert_eq!(loaded, HighScores::default());
+    assert!(!path.exists());
+    let aside = path.with_extension("ron.bad");
+    assert!(aside.exists());
+    fs::remove_file(aside).unwrap();
+
+    let newer = format!(
+        "(version: {}, entries: [])",
+        HIGH_SCORES_VERSION + 1
+    );
+    assert!(HighScores::from_ron(&newer).is_err());
+}
+
+#[test]
+fn hand_edited_tables_are_put_back_in_order() {
+    let contents = format!(
+        "(version: {HIGH_SCORES_VERSION}, entries: [
+            (score: 10),
+            (score: 30, name: Some(\"top\"), unknown: 1),
+            (score: 20),
+        ])"
+    );
+    let loaded = HighScores::from_ron(&contents).unwrap();
+    let scores: Vec<u32> =
+        loaded.0.iter().map(|entry| entry.score).collect();
+    assert_eq!(scores, vec![30, 20, 10]);
+    assert_eq!(loaded.0[0].name.as_deref(), Some("top"));
+}
+
+fn start_game() -> App {
+    let mut app = headless_app(3);
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    step(&mut app);
+    app
+}
+
+fn lose_the_game(app: &mut App) {
+    *app.world.resource_mut::<Lives>() = Lives(1);
+    let balls: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<Ball>>()
+        .iter(&app.world)
+        .collect();
+    for ball in balls {
+        app.world.despawn(ball);
+    }
+    step(app);
+    step(app);
+    assert_eq!(
+        app.world.resource::<State<GameState>>().0,
+        GameState::GameOver
+    );
+}
+
+#[test]
+fn finished_games_are_recorded() {
+    let mut app = start_game();
+    app.world.resource_mut::<Score>().score = 1234;
+    // as if the game had moved on from the level it
+    // started on
+    app.world.insert_resource(CurrentLevel(2));
+    lose_the_game(&mut app);
+
+    assert_eq!(
+        app.world.resource::<RunStats>().high_score_rank,
+        Some(0)
+    );
+    let best = app
+        .world
+        .resource::<HighScores>()
+        .best()
+        .unwrap()
+        .clone();
+    assert_eq!(
+        (best.score, best.seed, best.level),
+        (1234, 3, 2)
+    );
+    assert_eq!(best.name, None);
+}
+
+#[test]
+fn pointless_games_are_not_recorded() {
+    let mut app = start_game();
+    lose_the_game(&mut app);
+    assert_eq!(
+        app.world.resource::<RunStats>().high_score_rank,
+        None
+    );
+    assert!(app
+        .world
+        .resource::<HighScores>()
+        .0
+        .is_empty());
+}
@@ -0,0 +1,109 @@
+//! The HUD follows the game it sits over.
+use bevy::prelude::*;
+use block_breaker::{
+    assets::FontAssets,
+    gameplay::{headless_app, step, CurrentLevel},
+    hud::{HudField, HudPlugin},
+    powerups::ActivePowerups,
+    scoring::Score,
+    Ball, GameState, Lives, Powerup,
+};
+
+fn start_game() -> App {
+    let mut app = headless_app(0);
+    app.add_plugin(AssetPlugin::default())
+        .add_plugin(HudPlugin)
+        .insert_resource(FontAssets {
+            main: Handle::default(),
+        });
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    step(&mut app);
+    app
+}
+
+fn hud_text(app: &mut App, field: HudField) -> String {
+    app.world
+        .query::<(&Text, &HudField)>()
+        .iter(&app.world)
+        .find(|(_, other)| **other == field)
+        .map(|(text, _)| {
+            text.sections
+                .iter()
+                .map(|section| section.value.as_str())
+                .collect()
+        })
+        .unwrap()
+}
+
+#[test]
+fn the_hud_starts_filled_in() {
+    let mut app = start_game();
+    assert_eq!(
+        hud_text(&mut app, HudField::Score),
+        "Score 0"
+    );
+    assert_eq!(
+        hud_text(&mut app, HudField::HighScore),
+        "High 0"
+    );
+    assert_eq!(
+        hud_text(&mut app, HudField::Lives),
+        "Lives 3"
+    );
+    assert_eq!(
+        hud_text(&mut app, HudField::Level),
+        "Level 1: Rainbow"
+    );
+    assert_eq!(hud_text(&mut app, HudField::Time), "0:00");
+    assert_eq!(
+        hud_text(&mut app, HudField::Balls),
+        "Balls 1"
+    );
+    assert_eq!(hud_text(&mut app, HudField::Powerups), "");
+}
+
+#[test]
+fn the_hud_follows_the_game() {
+    let mut app = start_game();
+    app.world.resource_mut::<Score>().score = 250;
+    *app.world.resource_mut::<Lives>() = Lives(2);
+    app.world.resource_mut::<ActivePowerups>().activate(
+        Powerup::Magnet,
+        Powerup::Magnet.duration().unwrap(),
+    );
+    let ball = app
+        .world
+        .query_filtered::<Entity, With<Ball>>()
+        .single(&app.world);
+    app.world.despawn(ball);
+    app.world.insert_resource(CurrentLevel(2));
+    app.update();
+
+    assert_eq!(
+        hud_text(&mut app, HudField::Score),
+        "Score 250"
+    );
+    assert_eq!(
+        hud_text(&mut app, HudField::HighScore),
+        "High 250"
+    );
+    assert_eq!(
+        hud_text(&mut app, HudField::Lives),
+        "Lives 2"
+    );
+    // levels past the last one repeat it
+    assert_eq!(
+        hud_text(&mut app, HudField::Level),
+        "Level 2: Rainbow"
+    );
+    assert_eq!(
+        hud_text(&mut app, HudField::Balls),
+        "Balls 0"
+    );
+    assert_eq!(
+        hud_text(&mut app, HudField::Powerups),
+        "Magnet 12 "
+    );
+}
@@ -0,0 +1,171 @@
+//! Leaving a game must leave nothing of it behind, and
+//! pausing must keep all of it.
+use bevy::{ecs::system::Command, prelude::*};
+use block_breaker::{
+    blocks::Block,
+    custom_commands::{RestartGame, RestartLevel},
+    gameplay::{headless_app, step},
+    input::PlayerInput,
+    scoring::{RunStats, Score},
+    serve::Serving,
+    Ball, GameState, Lives, Paddle, StateScoped,
+};
+
+fn start_game() -> App {
+    let mut app = headless_app(0);
+    go_to(&mut app, GameState::Playing);
+    app
+}
+
+fn go_to(app: &mut App, state: GameState) {
+    app.world.insert_resource(NextState(Some(state)));
+    step(app);
+}
+
+fn count<T: Component>(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<T>>()
+        .iter(&app.world)
+        .count()
+}
+
+fn state(app: &App) -> GameState {
+    app.world.resource::<State<GameState>>().0
+}
+
+fn despawn_all<F: bevy::ecs::query::ReadOnlyWorldQuery>(
+    app: &mut App,
+) {
+    let entities: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, F>()
+        .iter(&app.world)
+        .collect();
+    for entity in entities {
+        app.world.despawn(entity);
+    }
+}
+
+/// Serve and let the ball fly for a second
+fn play_a_little(app: &mut App) {
+    app.world.resource_mut::<PlayerInput>().launch = true;
+    for _ in 0..60 {
+        step(app);
+    }
+}
+
+#[test]
+fn pausing_keeps_the_game() {
+    let mut app = start_game();
+    play_a_little(&mut app);
+    let scoped = count::<StateScoped>(&mut app);
+
+    go_to(&mut app, GameState::Paused);
+    assert_eq!(count::<StateScoped>(&mut app), scoped);
+    go_to(&mut app, GameState::Playing);
+    assert_eq!(count::<StateScoped>(&mut app), scoped);
+    assert_eq!(count::<Paddle>(&mut app), 1);
+}
+
+#[test]
+fn restarting_starts_over_without_duplicates() {
+    let mut app = start_game();
+    let scoped = count::<StateScoped>(&mut app);
+    play_a_little(&mut app);
+
+    go_to(&mut app, GameState::Paused);
+    RestartGame.write(&mut app.world);
+    step(&mut app);
+    assert_eq!(
+        app.world.resource::<State<GameState>>().0,
+        GameState::Playing
+    );
+    assert_eq!(count::<StateScoped>(&mut app), scoped);
+    assert_eq!(count::<Paddle>(&mut app), 1);
+    assert_eq!(count::<Ball>(&mut app), 1);
+}
+
+#[test]
+fn restarting_the_level_keeps_the_score() {
+    let mut app = start_game();
+    let blocks = count::<Block>(&mut app);
+    play_a_little(&mut app);
+    // as if a few had been broken
+    let broken: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<Block>>()
+        .iter(&app.world)
+        .take(5)
+        .collect();
+    for block in broken {
+        app.world.despawn(block);
+    }
+    app.world.resource_mut::<Score>().score = 500;
+    app.world.resource_mut::<Lives>().0 = 2;
+    app.world.resource_mut::<RunStats>().blocks_broken = 7;
+
+    go_to(&mut app, GameState::Paused);
+    RestartLevel.write(&mut app.world);
+    step(&mut app);
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(app.world.resource::<Score>().score, 500);
+    assert_eq!(app.world.resource::<Lives>().0, 2);
+    assert_eq!(
+        app.world.resource::<RunStats>().blocks_broken,
+        7
+    );
+    assert_eq!(count::<Block>(&mut app), blocks);
+    assert_eq!(count::<Paddle>(&mut app), 1);
+    assert_eq!(count::<Ball>(&mut app), 1);
+    assert_eq!(count::<Serving>(&mut app), 1);
+}
+
+#[test]
+fn quitting_to_the_menu_clears_the_game() {
+    let mut app = start_game();
+    play_a_little(&mut app);
+
+    go_to(&mut app, GameState::Paused);
+    go_to(&mut app, GameState::Menu);
+    assert_eq!(count::<StateScoped>(&mut app), 0);
+
+    // and a new game can start afterwards
+    go_to(&mut app, GameState::Playing);
+    assert_eq!(count::<Paddle>(&mut app), 1);
+    assert_eq!(count::<Ball>(&mut app), 1);
+}
+
+#[test]
+fn losing_the_last_ball_ends_the_game() {
+    let mut app = start_game();
+    *app.world.resource_mut::<Lives>() = Lives(1);
+    despawn_all::<With<Ball>>(&mut app);
+    step(&mut app);
+    step(&mut app);
+    assert_eq!(state(&app), GameState::GameOver);
+    assert_eq!(count::<Paddle>(&mut app), 0);
+
+    RestartGame.write(&mut app.world);
+    step(&mut app);
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(app.world.resource::<Lives>().0, 3);
+    assert_eq!(count::<Paddle>(&mut app), 1);
+}
+
+#[test]
+fn breaking_every_block_wins() {
+    let mut app = start_game();
+    let breakable: Vec<Entity> = app
+        .world
+        .query::<(Entity, &Block)>()
+        .iter(&app.world)
+        .filter(|(_, block)| block.is_destructible())
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in breakable {
+        app.world.despawn(entity);
+    }
+    step(&mut app);
+    step(&mut app);
+    assert_eq!(state(&app), GameState::Victory);
+}
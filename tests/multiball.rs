@@ -0,0 +1,58 @@
+//! Multiball splits the balls in flight without ever
+//! going over the cap.
+use bevy::prelude::*;
+use block_breaker::{
+    ball::Multiball,
+    gameplay::{headless_app, step},
+    input::PlayerInput,
+    serve::Serving,
+    Ball, GameState, MultiballEvent,
+};
+
+fn start_game() -> App {
+    let mut app = headless_app(0);
+    app.insert_resource(Multiball {
+        max_balls: 4,
+        ..default()
+    });
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    step(&mut app);
+    app
+}
+
+fn count_balls(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<Ball>>()
+        .iter(&app.world)
+        .count()
+}
+
+#[test]
+fn two_pickups_in_one_tick_respect_the_cap() {
+    let mut app = start_game();
+    app.world.resource_mut::<PlayerInput>().launch = true;
+    step(&mut app);
+    assert_eq!(count_balls(&mut app), 1);
+
+    app.world.send_event(MultiballEvent);
+    app.world.send_event(MultiballEvent);
+    step(&mut app);
+    assert_eq!(count_balls(&mut app), 4);
+}
+
+#[test]
+fn a_ball_being_served_is_not_split() {
+    let mut app = start_game();
+    app.world.send_event(MultiballEvent);
+    step(&mut app);
+    assert_eq!(count_balls(&mut app), 1);
+    assert_eq!(
+        app.world
+            .query_filtered::<(), (With<Ball>, With<Serving>)>()
+            .iter(&app.world)
+            .count(),
+        1
+    );
+}
@@ -0,0 +1,203 @@
+//! Playing a recorded game back must end exactly where
+//! the original game did.
+use bevy::{ecs::system::Command, prelude::*};
+use block_breaker::{
+    blocks::Block,
+    custom_commands::RestartGame,
+    gameplay::{headless_app, step},
+    input::PlayerInput,
+    replay::{
+        play_back, Playback, Recording, Replay,
+        REPLAY_VERSION,
+    },
+    serve::Serving,
+    Ball, Difficulty, GameSeed, GameState, Lives, Paddle,
+    SeedSource,
+};
+
+/// Everything that would differ if playback drifted
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    paddle: Vec3,
+    balls: Vec<Vec3>,
+    blocks: usize,
+    lives: u32,
+}
+
+fn snapshot(app: &mut App) -> Snapshot {
+    let paddle = app
+        .world
+        .query_filtered::<&Transform, With<Paddle>>()
+        .single(&app.world)
+        .translation;
+    let balls = app
+        .world
+        .query_filtered::<&Transform, With<Ball>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation)
+        .collect();
+    let blocks = app
+        .world
+        .query::<&Block>()
+        .iter(&app.world)
+        .count();
+    Snapshot {
+        paddle,
+        balls,
+        blocks,
+        lives: app.world.resource::<Lives>().0,
+    }
+}
+
+/// Keep the paddle under the lowest ball, a little off
+/// center so its bounces vary, and serve right away
+fn scripted_input(
+    app: &mut App,
+    tick: usize,
+) -> PlayerInput {
+    let lowest_ball = app
+        .world
+        .query_filtered::<&Transform, With<Ball>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation)
+        .min_by(|a, b| a.y.total_cmp(&b.y));
+    let offset = ((tick / 200) % 3) as f32 * 30.0 - 30.0;
+    PlayerInput {
+        movement: 0.0,
+        target_x: lowest_ball.map(|ball| ball.x + offset),
+        launch: tick % 60 == 30,
+        fire: false,
+    }
+}
+
+fn record_game(
+    seed: u64,
+    ticks: usize,
+) -> (Replay, Snapshot) {
+    let mut app = headless_app(seed);
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    for tick in 0..ticks {
+        let input = scripted_input(&mut app, tick);
+        *app.world.resource_mut::<PlayerInput>() = input;
+        step(&mut app);
+    }
+    let replay =
+        app.world.resource::<Recording>().0.clone();
+    (replay, snapshot(&mut app))
+}
+
+#[test]
+fn playback_reproduces_the_recorded_game() {
+    let (replay, recorded) = record_game(42, 1800);
+    assert_eq!(replay.inputs.len(), 1800);
+    assert!(
+        !recorded.balls.is_empty(),
+        "the game should still be going"
+    );
+
+    let mut app = play_back(replay);
+    assert_eq!(snapshot(&mut app), recorded);
+}
+
+#[test]
+fn replays_survive_a_round_trip_through_a_file() {
+    let (replay, _) = record_game(7, 300);
+    let path = std::env::temp_dir().join(format!(
+        "block_breaker_replay_{}.ron",
+        std::process::id()
+    ));
+    replay.save(&path).unwrap();
+    let loaded = Replay::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), replay);
+}
+
+#[test]
+fn replays_from_other_versions_are_rejected() {
+    let replay = Replay {
+        version: REPLAY_VERSION + 1,
+        ..Default::default()
+    };
+    let path = std::env::temp_dir().join(format!(
+        "block_breaker_old_replay_{}.ron",
+        std::process::id()
+    ));
+    replay.save(&path).unwrap();
+    let loaded = Replay::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(loaded.is_err());
+}
+
+#[test]
+fn replays_keep_the_difficulty() {
+    let mut app = headless_app(5);
+    app.insert_resource(Difficulty::Easy);
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    step(&mut app);
+    let replay =
+        app.world.resource::<Recording>().0.clone();
+    assert_eq!(replay.difficulty, Difficulty::Easy);
+
+    let app = play_back(replay);
+    assert_eq!(
+        *app.world.resource::<Difficulty>(),
+        Difficulty::Easy
+    );
+}
+
+#[test]
+fn the_player_takes_over_once_a_replay_ends() {
+    // short enough that the ball is never served
+    let (replay, _) = record_game(3, 10);
+    let mut app = play_back(replay);
+    assert!(!app.world.contains_resource::<Playback>());
+
+    app.world.insert_resource(NextState(Some(
+        GameState::Paused,
+    )));
+    step(&mut app);
+    RestartGame.write(&mut app.world);
+    step(&mut app);
+    app.world.resource_mut::<PlayerInput>().launch = true;
+    step(&mut app);
+    assert_eq!(
+        app.world
+            .query_filtered::<(), With<Serving>>()
+            .iter(&app.world)
+            .count(),
+        0,
+        "the serve ignored the player's input"
+    );
+}
+
+#[test]
+fn every_game_gets_its_own_seed() {
+    let mut app = headless_app(0);
+    app.insert_resource(SeedSource::Random);
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    step(&mut app);
+    let first = *app.world.resource::<GameSeed>();
+    assert_eq!(
+        app.world.resource::<Recording>().0.seed,
+        first.0
+    );
+
+    app.world.insert_resource(NextState(Some(
+        GameState::Paused,
+    )));
+    step(&mut app);
+    RestartGame.write(&mut app.world);
+    step(&mut app);
+    let second = *app.world.resource::<GameSeed>();
+    assert_ne!(first, second);
+    assert_eq!(
+        app.world.resource::<Recording>().0.seed,
+        second.0
+    );
+}
@@ -0,0 +1,213 @@
+//! Points come from the scoring rules and every award is
+//! announced with a `ScoreChanged`. Blocks broken in a
+//! row multiply the points.
+use std::{path::Path, time::Duration};
+
+use bevy::{asset::LoadState, prelude::*};
+use block_breaker::{
+    blocks::Block,
+    gameplay::{headless_app, step},
+    scoring::{
+        Combo, RunStats, Score, ScoreChanged, ScoringRules,
+        ScoringRulesLoader,
+    },
+    serve::Serving,
+    Ball, ConnectToPaddle, Damage, DespawnArea, GameState,
+};
+
+fn start_game() -> App {
+    let mut app = headless_app(0);
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    step(&mut app);
+    app
+}
+
+fn shipped_rules() -> ScoringRules {
+    ScoringRules::load_from(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("scoring.rules.ron"),
+    )
+    .unwrap()
+}
+
+#[test]
+fn shipped_rules_load_as_an_asset() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<ScoringRules>()
+        .init_asset_loader::<ScoringRulesLoader>();
+    let handle: Handle<ScoringRules> = app
+        .world
+        .resource::<AssetServer>()
+        .load("scoring.rules.ron");
+    for _ in 0..100 {
+        app.update();
+        let state = app
+            .world
+            .resource::<AssetServer>()
+            .get_load_state(&handle);
+        if state != LoadState::Loading {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(
+        app.world
+            .resource::<Assets<ScoringRules>>()
+            .get(&handle),
+        Some(&shipped_rules())
+    );
+}
+
+#[test]
+fn breaking_a_block_awards_its_points() {
+    let mut app = start_game();
+    let (entity, block) = app
+        .world
+        .query::<(Entity, &Block)>()
+        .iter(&app.world)
+        .map(|(entity, block)| (entity, *block))
+        .find(|(_, block)| {
+            block.is_destructible()
+                && *block != Block::Silver
+        })
+        .unwrap();
+    app.world.entity_mut(entity).insert(Damage(1));
+    step(&mut app);
+
+    let points = shipped_rules().block_points(block);
+    assert_eq!(app.world.resource::<Score>().score, points);
+    let events =
+        app.world.resource::<Events<ScoreChanged>>();
+    let sent: Vec<ScoreChanged> =
+        events.get_reader().iter(events).copied().collect();
+    assert_eq!(
+        sent,
+        vec![ScoreChanged {
+            points,
+            total: points
+        }]
+    );
+}
+
+#[test]
+fn clear_bonus_rewards_lives_and_speed() {
+    let rules = shipped_rules();
+    let par = rules.clear_par_time;
+    assert_eq!(rules.clear_bonus(0, par), rules.clear);
+    assert_eq!(
+        rules.clear_bonus(2, par + Duration::from_secs(60)),
+        rules.clear + 2 * rules.clear_per_life
+    );
+    assert_eq!(
+        rules.clear_bonus(0, par - Duration::from_secs(30)),
+        rules.clear + 30 * rules.clear_per_second
+    );
+}
+
+#[test]
+fn multiplier_grows_with_the_combo_up_to_a_cap() {
+    let rules = ScoringRules {
+        combo_step: 2,
+        max_multiplier: 3,
+        ..shipped_rules()
+    };
+    let multipliers: Vec<u32> = (0..=7)
+        .map(|combo| rules.multiplier(combo))
+        .collect();
+    assert_eq!(multipliers, vec![1, 1, 1, 2, 2, 3, 3, 3]);
+    assert_eq!(
+        rules.combo_points(Block::Red, 3),
+        2 * rules.block_points(Block::Red)
+    );
+}
+
+/// Break `count` blocks one tick after another and
+/// return which kinds they were
+fn break_blocks(app: &mut App, count: usize) -> Vec<Block> {
+    let blocks: Vec<(Entity, Block)> = app
+        .world
+        .query::<(Entity, &Block)>()
+        .iter(&app.world)
+        .filter(|(_, block)| {
+            block.is_destructible()
+                && **block != Block::Silver
+        })
+        .map(|(entity, block)| (entity, *block))
+        .take(count)
+        .collect();
+    for (entity, _) in &blocks {
+        app.world.entity_mut(*entity).insert(Damage(1));
+        step(app);
+    }
+    blocks.into_iter().map(|(_, block)| block).collect()
+}
+
+#[test]
+fn consecutive_breaks_build_a_combo() {
+    let mut app = start_game();
+    let rules = shipped_rules();
+    let breaks = rules.combo_step as usize + 1;
+    let broken = break_blocks(&mut app, breaks);
+
+    assert_eq!(
+        app.world.resource::<Combo>().0,
+        breaks as u32
+    );
+    assert_eq!(
+        app.world.resource::<RunStats>().best_combo,
+        breaks as u32
+    );
+    // the last break was worth double
+    let expected: u32 = broken
+        .iter()
+        .enumerate()
+        .map(|(i, block)| {
+            let points = rules.block_points(*block);
+            if i + 1 == breaks {
+                2 * points
+            } else {
+                points
+            }
+        })
+        .sum();
+    assert_eq!(
+        app.world.resource::<Score>().score,
+        expected
+    );
+}
+
+#[test]
+fn losing_a_ball_ends_the_combo() {
+    let mut app = start_game();
+    break_blocks(&mut app, 3);
+    assert_eq!(app.world.resource::<Combo>().0, 3);
+
+    let below = app
+        .world
+        .query_filtered::<&Transform, With<DespawnArea>>()
+        .single(&app.world)
+        .translation;
+    let mut balls = app
+        .world
+        .query_filtered::<(Entity, &mut Transform), With<Ball>>();
+    let (ball, mut transform) =
+        balls.single_mut(&mut app.world);
+    transform.translation = below;
+    app.world
+        .entity_mut(ball)
+        .remove::<(ConnectToPaddle, Serving)>();
+    for _ in 0..5 {
+        step(&mut app);
+    }
+    assert_eq!(app.world.resource::<Combo>().0, 0);
+    // the best combo is kept for the summary
+    assert_eq!(
+        app.world.resource::<RunStats>().best_combo,
+        3
+    );
+}
@@ -0,0 +1,184 @@
+//! Balls fired far faster than they travel in play must
+//! still bounce off of everything they hit.
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use block_breaker::{
+    ball::BallSpeed,
+    blocks::Block,
+    board::Board,
+    custom_commands::SpawnBall,
+    gameplay::{headless_app, step},
+    Ball, GameState, Paddle,
+};
+
+/// About 100 pixels a tick, several times the
+/// thickness of a block
+const SPEED: f32 = 6000.0;
+
+fn start_game() -> App {
+    let mut app = headless_app(0);
+    app.world.insert_resource(NextState(Some(
+        GameState::Playing,
+    )));
+    step(&mut app);
+    // leave only the balls under test
+    let serving: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<Ball>>()
+        .iter(&app.world)
+        .collect();
+    for ball in serving {
+        app.world.despawn(ball);
+    }
+    app.world.resource_mut::<BallSpeed>().base = SPEED;
+    app
+}
+
+fn fire(
+    app: &mut App,
+    from: Vec2,
+    direction: Vec2,
+) -> Entity {
+    SpawnBall {
+        velocity: Velocity::linear(
+            direction.normalize() * SPEED,
+        ),
+        transform: Transform::from_translation(
+            from.extend(5.0),
+        ),
+        fireball: false,
+    }
+    .spawn(&mut app.world)
+}
+
+fn ball_state(
+    app: &App,
+    ball: Entity,
+) -> Option<(Vec2, Vec2)> {
+    let entity = app.world.get_entity(ball)?;
+    Some((
+        entity.get::<Transform>()?.translation.truncate(),
+        entity.get::<Velocity>()?.linvel,
+    ))
+}
+
+#[test]
+fn balls_bounce_off_the_lowest_row_of_blocks() {
+    let mut app = start_game();
+    let mut lowest_row: Vec<Vec2> = app
+        .world
+        .query_filtered::<&Transform, With<Block>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    let bottom = lowest_row
+        .iter()
+        .map(|position| position.y)
+        .fold(f32::INFINITY, f32::min);
+    lowest_row.retain(|position| position.y == bottom);
+    assert!(!lowest_row.is_empty());
+
+    for target in lowest_row {
+        let ball = fire(
+            &mut app,
+            Vec2::new(target.x, bottom - 300.0),
+            Vec2::Y,
+        );
+        for _ in 0..30 {
+            step(&mut app);
+            let (position, velocity) =
+                ball_state(&app, ball)
+                    .expect("ball left play");
+            assert!(
+                position.y < bottom,
+                "ball tunneled into the block at {target}"
+            );
+            if velocity.y < 0.0 {
+                break;
+            }
+        }
+        let (_, velocity) = ball_state(&app, ball).unwrap();
+        assert!(
+            velocity.y < 0.0,
+            "ball never reached {target}"
+        );
+        app.world.despawn(ball);
+    }
+}
+
+#[test]
+fn balls_bounce_off_the_paddle() {
+    let mut app = start_game();
+    let paddle = app
+        .world
+        .query_filtered::<&Transform, With<Paddle>>()
+        .single(&app.world)
+        .translation
+        .truncate();
+
+    for offset in [-80.0, -40.0, 0.0, 40.0, 80.0] {
+        let ball = fire(
+            &mut app,
+            Vec2::new(paddle.x + offset, paddle.y + 400.0),
+            Vec2::NEG_Y,
+        );
+        for _ in 0..30 {
+            step(&mut app);
+            let (position, velocity) = ball_state(
+                &app, ball,
+            )
+            .expect("ball tunneled through the paddle");
+            assert!(
+                position.y > paddle.y,
+                "ball tunneled through the paddle at {offset}"
+            );
+            if velocity.y > 0.0 {
+                break;
+            }
+        }
+        let (position, velocity) =
+            ball_state(&app, ball).unwrap();
+        assert!(
+            velocity.y > 0.0,
+            "ball never bounced off the paddle at {offset}"
+        );
+        assert!(
+            position.y > paddle.y,
+            "ball ended up below the paddle at {offset}"
+        );
+        app.world.despawn(ball);
+    }
+}
+
+#[test]
+fn balls_stay_inside_the_board() {
+    let mut app = start_game();
+    let board = app.world.resource::<Board>().clone();
+    let center =
+        Vec2::new(board.physical.x, board.physical.y) / 2.0;
+    let balls: Vec<Entity> = (0..16)
+        .map(|i| {
+            let angle =
+                i as f32 / 16.0 * std::f32::consts::TAU;
+            fire(&mut app, center, Vec2::from_angle(angle))
+        })
+        .collect();
+
+    for _ in 0..240 {
+        step(&mut app);
+        for ball in balls.iter() {
+            // balls that fall out of the bottom are gone
+            let Some((position, _)) =
+                ball_state(&app, *ball)
+            else {
+                continue;
+            };
+            assert!(
+                position.x > 0.0
+                    && position.x < board.physical.x
+                    && position.y < board.physical.y,
+                "ball escaped the board at {position}"
+            );
+        }
+    }
+}